@@ -1,34 +1,90 @@
-use anyhow::{anyhow, Result};
-use std::fs;
+use anyhow::{anyhow, Context, Result};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
 
 static INPUT_PATH: &str = "input.txt";
 
-fn load_input(path: &str) -> Result<Vec<u16>> {
-    let raw = fs::read_to_string(path)?;
+/// Read the depths from the file at `path`
+pub fn load_input(path: &str) -> Result<Vec<u16>> {
+    let file = File::open(path).with_context(|| format!("reading input {}", path))?;
 
-    raw.lines()
-        .filter(|s| !s.is_empty())
-        .map(|s| s.parse::<u16>())
-        .map(|r| r.map_err(|e| anyhow!(e)))
+    load_from_reader(BufReader::new(file))
+}
+
+/// Parse one depth per non-empty line
+pub fn load_from_reader<R: BufRead>(r: R) -> Result<Vec<u16>> {
+    r.lines()
+        .filter(|l| !matches!(l, Ok(s) if s.is_empty()))
+        .map(|l| l?.parse::<u16>().map_err(|e| anyhow!(e)))
         .collect()
 }
 
-fn count_increases(values: &[u16]) -> usize {
-    values.windows(2).filter(|v| v[0] < v[1]).count()
+/// Count increases between the sums of consecutive windows of `window` values
+pub fn windowed_increases<T: Copy + Into<i64>>(values: &[T], window: usize) -> usize {
+    if window == 0 || window > values.len() {
+        return 0;
+    }
+
+    // i64 running sum, a window of readings near the max of their type would wrap otherwise
+    let first_sum: i64 = values[..window].iter().map(|v| (*v).into()).sum();
+
+    let (_, increases) = values.iter().zip(values[window..].iter()).fold(
+        (first_sum, 0),
+        |(sum, increases), (leaving, entering)| {
+            let next = sum + (*entering).into() - (*leaving).into();
+
+            (next, increases + (next > sum) as usize)
+        },
+    );
+
+    increases
+}
+
+/// Number of measurements larger than the previous one
+pub fn count_increases<T: Copy + Into<i64>>(values: &[T]) -> usize {
+    windowed_increases(values, 1)
 }
 
-fn three_measurements(values: &[u16]) -> usize {
-    let three_measurement_windows: Vec<u16> =
-        values.windows(3).map(|w| w.into_iter().sum()).collect();
+/// Number of measurements smaller than the previous one
+pub fn count_decreases<T: Copy + Into<i64>>(values: &[T]) -> usize {
+    values
+        .windows(2)
+        .filter(|v| v[0].into() > v[1].into())
+        .count()
+}
 
-    count_increases(&three_measurement_windows)
+/// Number of measurements equal to the previous one
+pub fn count_equal<T: Copy + Into<i64>>(values: &[T]) -> usize {
+    values
+        .windows(2)
+        .filter(|v| v[0].into() == v[1].into())
+        .count()
+}
+
+fn three_measurements<T: Copy + Into<i64>>(values: &[T]) -> usize {
+    windowed_increases(values, 3)
+}
+
+/// Increases of single measurements and of three-measurement windows
+pub fn solve(values: &[u16]) -> (usize, usize) {
+    (count_increases(values), three_measurements(values))
 }
 
 fn main() -> Result<()> {
-    let input = load_input(INPUT_PATH)?;
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| INPUT_PATH.to_owned());
+    let input = match path.as_str() {
+        "-" => load_from_reader(io::stdin().lock())?,
+        _ => load_input(&path)?,
+    };
 
-    println!("Part 1: {}", count_increases(&input));
-    println!("Part 1: {}", three_measurements(&input));
+    let (part_1, part_2) = solve(&input);
+
+    println!("Part 1: {}", part_1);
+    println!("Part 2: {}", part_2);
 
     Ok(())
 }
@@ -36,4 +92,91 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod test {
     use super::*;
+
+    static SAMPLE_DEPTHS: [u16; 10] = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+
+    #[test]
+    fn windowed_increases_sample() {
+        assert_eq!(windowed_increases(&SAMPLE_DEPTHS, 1), 7);
+        assert_eq!(windowed_increases(&SAMPLE_DEPTHS, 2), 5);
+        assert_eq!(windowed_increases(&SAMPLE_DEPTHS, 3), 5);
+        assert_eq!(windowed_increases(&SAMPLE_DEPTHS, 11), 0);
+
+        assert_eq!(count_increases(&SAMPLE_DEPTHS), 7);
+        assert_eq!(three_measurements(&SAMPLE_DEPTHS), 5);
+    }
+
+    #[test]
+    fn three_measurements_no_overflow() {
+        let values = [60000, 60000, 60000, 60001];
+
+        assert_eq!(three_measurements(&values), 1);
+
+        // window sums beyond u32
+        let mut values = vec![u16::MAX; 70_001];
+        values[0] = 0;
+        assert_eq!(windowed_increases(&values, 70_000), 1);
+    }
+
+    #[test]
+    fn windowed_increases_matches_naive() {
+        let mut seed: u32 = 42;
+        let values: Vec<u16> = (0..100_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                ((seed >> 16) % 20_000) as u16
+            })
+            .collect();
+
+        let naive_sums: Vec<u64> = values
+            .windows(3)
+            .map(|w| w.iter().map(|v| *v as u64).sum())
+            .collect();
+        let naive = |v: &[u64]| v.windows(2).filter(|w| w[0] < w[1]).count();
+
+        assert_eq!(windowed_increases(&values, 3), naive(&naive_sums));
+
+        let singles: Vec<u64> = values.iter().map(|v| *v as u64).collect();
+        assert_eq!(count_increases(&values), naive(&singles));
+    }
+
+    #[test]
+    fn count_steps_by_direction() {
+        let values = [1, 2, 2, 5, 3, 3, 3, 1, 4];
+
+        assert_eq!(count_increases(&values), 3);
+        assert_eq!(count_decreases(&values), 2);
+        assert_eq!(count_equal(&values), 3);
+    }
+
+    #[test]
+    fn load_input_missing_file() {
+        let err = load_input("does/not/exist.txt").unwrap_err();
+
+        assert_eq!(err.to_string(), "reading input does/not/exist.txt");
+    }
+
+    #[test]
+    fn load_from_reader_in_memory() {
+        let raw: &[u8] = b"199\n200\n\n208\n";
+
+        assert_eq!(load_from_reader(raw).unwrap(), vec![199, 200, 208]);
+        assert!(load_from_reader(&b"199\nabc\n"[..]).is_err());
+    }
+
+    #[test]
+    fn count_increases_i32() {
+        let values: [i32; 6] = [-5, -3, -4, 0, -1, 7];
+
+        assert_eq!(count_increases(&values), 3);
+        assert_eq!(three_measurements(&values), 3);
+        assert_eq!(count_increases(&[1u32, 2, 2, 3]), 2);
+        assert_eq!(count_decreases(&values), 2);
+        assert_eq!(count_equal(&[1u32, 2, 2, 3]), 1);
+    }
+
+    #[test]
+    fn solve_sample() {
+        assert_eq!(solve(&SAMPLE_DEPTHS), (7, 5));
+    }
 }
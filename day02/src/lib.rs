@@ -0,0 +1,275 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Forward(i32),
+    Backward(i32),
+    Up(i32),
+    Down(i32),
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let split: Vec<&str> = s.split_whitespace().collect();
+
+        let command = split
+            .first()
+            .ok_or(anyhow!("Command not found"))?
+            .to_lowercase();
+
+        let value_raw = *split.get(1).ok_or(anyhow!("Value not found"))?;
+        let value = value_raw.trim().parse::<i32>()?;
+
+        match command.as_str() {
+            "forward" => Ok(Command::Forward(value)),
+            "backward" => Ok(Command::Backward(value)),
+            "up" => Ok(Command::Up(value)),
+            "down" => Ok(Command::Down(value)),
+            _ => Err(anyhow!("Unknown Command")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub depth: i32,
+    pub h_pos: i32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Submarine {
+    pub depth: i32,
+    pub h_pos: i32,
+    pub aim: i32,
+}
+
+impl Submarine {
+    /// Apply a command with aim, up and down only change the aim
+    pub fn apply(&mut self, cmd: Command) {
+        match cmd {
+            Command::Forward(v) => {
+                self.h_pos += v;
+                self.depth += self.aim * v;
+            }
+            Command::Backward(v) => {
+                self.h_pos -= v;
+                self.depth -= self.aim * v;
+            }
+            Command::Up(v) => self.aim -= v,
+            Command::Down(v) => self.aim += v,
+        }
+    }
+
+    /// Apply a command without aim, up and down change the depth directly
+    pub fn apply_simple(&mut self, cmd: Command) {
+        match cmd {
+            Command::Forward(v) => self.h_pos += v,
+            Command::Backward(v) => self.h_pos -= v,
+            Command::Up(v) => self.depth -= v,
+            Command::Down(v) => self.depth += v,
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        Position {
+            depth: self.depth,
+            h_pos: self.h_pos,
+        }
+    }
+
+    pub fn product(&self) -> i32 {
+        self.depth * self.h_pos
+    }
+}
+
+pub fn part_1(commands: &[Command]) -> i32 {
+    let submarine = commands
+        .iter()
+        .fold(Submarine::default(), |mut submarine, command| {
+            submarine.apply_simple(*command);
+            submarine
+        });
+
+    submarine.product()
+}
+
+pub fn part_2(commands: &[Command]) -> i32 {
+    let submarine = commands
+        .iter()
+        .fold(Submarine::default(), |mut submarine, command| {
+            submarine.apply(*command);
+            submarine
+        });
+
+    submarine.product()
+}
+
+pub fn trajectory(commands: &[Command]) -> Vec<Position> {
+    commands
+        .iter()
+        .scan(Submarine::default(), |submarine, command| {
+            submarine.apply(*command);
+
+            Some(submarine.position())
+        })
+        .collect()
+}
+
+/// Final positions under the simple model and the aim model in a single pass
+pub fn dual_simulate(commands: &[Command]) -> (Position, Position) {
+    let (simple, aimed) = commands.iter().fold(
+        (Submarine::default(), Submarine::default()),
+        |(mut simple, mut aimed), command| {
+            simple.apply_simple(*command);
+            aimed.apply(*command);
+
+            (simple, aimed)
+        },
+    );
+
+    (simple.position(), aimed.position())
+}
+
+/// Total forward movement and net vertical movement (down positive)
+pub fn net_movement(commands: &[Command]) -> (i32, i32) {
+    commands
+        .iter()
+        .fold((0, 0), |(forward, vertical), command| match command {
+            Command::Forward(v) => (forward + v, vertical),
+            Command::Backward(v) => (forward - v, vertical),
+            Command::Up(v) => (forward, vertical - v),
+            Command::Down(v) => (forward, vertical + v),
+        })
+}
+
+pub fn load_input(path: &str) -> Result<Vec<Command>> {
+    let raw = fs::read_to_string(path)?;
+
+    parse_commands(&raw)
+}
+
+pub fn parse_commands(raw: &str) -> Result<Vec<Command>> {
+    raw.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(idx, line)| {
+            Command::from_str(line).with_context(|| format!("line {}: {:?}", idx + 1, line))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn command_parse_valid() {
+        let expected = Command::Down(4);
+
+        let command = Command::from_str("down 4").expect("error parsing command");
+
+        assert_eq!(command, expected);
+    }
+
+    #[test]
+    fn command_parse_case_and_whitespace() {
+        assert_eq!(Command::from_str("DOWN 4").unwrap(), Command::Down(4));
+        assert_eq!(Command::from_str(" up  7 ").unwrap(), Command::Up(7));
+        assert_eq!(
+            Command::from_str("left 2").unwrap_err().to_string(),
+            "Unknown Command"
+        );
+    }
+
+    static SAMPLE_COMMANDS: [Command; 6] = [
+        Command::Forward(5),
+        Command::Down(5),
+        Command::Forward(8),
+        Command::Up(3),
+        Command::Down(8),
+        Command::Forward(2),
+    ];
+
+    #[test]
+    fn trajectory_sample() {
+        let positions = trajectory(&SAMPLE_COMMANDS);
+
+        assert_eq!(positions.len(), SAMPLE_COMMANDS.len());
+
+        let last = positions.last().unwrap();
+        assert_eq!(
+            last,
+            &Position {
+                depth: 60,
+                h_pos: 15
+            }
+        );
+        assert_eq!(last.depth * last.h_pos, part_2(&SAMPLE_COMMANDS));
+    }
+
+    #[test]
+    fn net_movement_sample() {
+        assert_eq!(net_movement(&SAMPLE_COMMANDS), (15, 10));
+    }
+
+    #[test]
+    fn dual_simulate_sample() {
+        let (simple, aimed) = dual_simulate(&SAMPLE_COMMANDS);
+
+        assert_eq!(simple.depth * simple.h_pos, part_1(&SAMPLE_COMMANDS));
+        assert_eq!(aimed.depth * aimed.h_pos, part_2(&SAMPLE_COMMANDS));
+        assert_eq!(Some(&aimed), trajectory(&SAMPLE_COMMANDS).last());
+        assert_eq!(
+            simple,
+            Position {
+                depth: 10,
+                h_pos: 15
+            }
+        );
+    }
+
+    #[test]
+    fn submarine_modes() {
+        let mut simple = Submarine::default();
+        let mut aimed = Submarine::default();
+
+        SAMPLE_COMMANDS.iter().for_each(|command| {
+            simple.apply_simple(*command);
+            aimed.apply(*command);
+        });
+
+        assert_eq!(simple.product(), 150);
+        assert_eq!(aimed.product(), 900);
+        assert_eq!(aimed.aim, 10);
+
+        assert_eq!(part_1(&SAMPLE_COMMANDS), 150);
+        assert_eq!(part_2(&SAMPLE_COMMANDS), 900);
+    }
+
+    #[test]
+    fn parse_commands_reports_line() {
+        let err = parse_commands("forward 5\nsideways 3\nup 2").unwrap_err();
+
+        assert_eq!(err.to_string(), "line 2: \"sideways 3\"");
+        assert_eq!(err.root_cause().to_string(), "Unknown Command");
+    }
+
+    #[test]
+    fn backward_commands() {
+        assert_eq!(
+            Command::from_str("backward 3").unwrap(),
+            Command::Backward(3)
+        );
+
+        let commands = parse_commands("forward 5\ndown 2\nforward 4\nbackward 3\nup 1").unwrap();
+
+        // simple: h_pos 6, depth 1 / aim: h_pos 6, depth 2 * 4 - 2 * 3 = 2
+        assert_eq!(part_1(&commands), 6);
+        assert_eq!(part_2(&commands), 12);
+        assert_eq!(net_movement(&commands), (6, 1));
+    }
+}
@@ -0,0 +1,10 @@
+use day02::{parse_commands, part_1, part_2, Command};
+
+#[test]
+fn small_program_both_parts() {
+    let commands = parse_commands("forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2").unwrap();
+
+    assert_eq!(commands.first(), Some(&Command::Forward(5)));
+    assert_eq!(part_1(&commands), 150);
+    assert_eq!(part_2(&commands), 900);
+}
@@ -8,86 +8,130 @@ pub trait FromBinaryString {
     fn is_bit_set(&self, n: usize, num_bits: usize) -> bool;
 }
 
-impl FromBinaryString for i32 {
-    fn from_binary_str(s: &str) -> Result<i32> {
-        i32::from_str_radix(s, 2).map_err(|e| anyhow!(e))
-    }
+macro_rules! impl_from_binary_string {
+    ($($t:ty),*) => {$(
+        impl FromBinaryString for $t {
+            fn from_binary_str(s: &str) -> Result<$t> {
+                <$t>::from_str_radix(s, 2).map_err(|e| anyhow!(e))
+            }
+
+            fn is_bit_set(&self, n: usize, num_bits: usize) -> bool {
+                assert!(n < num_bits, "bit {} out of range for {} bits", n, num_bits);
+                assert!(num_bits <= <$t>::BITS as usize, "{} bits do not fit", num_bits);
+
+                let mask: $t = 1 << (num_bits - 1 - n);
 
-    fn is_bit_set(&self, n: usize, num_bits: usize) -> bool {
-        if n > 11 {
-            panic!("is_bit_set_12 with value > 11")
+                self & mask != 0
+            }
         }
+    )*};
+}
 
-        let mask = 1 << (num_bits - 1 - n);
+impl_from_binary_string!(i32, u32, u64);
+
+/// Common bit width of all lines, errors on the first ragged or non binary line
+pub fn validate_width(lines: &[String]) -> Result<usize> {
+    let width = match lines.first() {
+        Some(first) if !first.is_empty() => first.len(),
+        _ => bail!("line 1: empty input"),
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.len() != width {
+            bail!(
+                "line {}: \"{}\" has {} bits, expected {}",
+                i + 1,
+                line,
+                line.len(),
+                width
+            );
+        }
 
-        self & mask != 0
+        if let Some(c) = line.chars().find(|c| *c != '0' && *c != '1') {
+            bail!(
+                "line {}: \"{}\" contains non binary char '{}'",
+                i + 1,
+                line,
+                c
+            );
+        }
     }
+
+    Ok(width)
 }
 
-fn calc_cols(lines: &[String]) -> [i32; 12] {
-    let mut cols = [0; 12];
+/// Number of set bits per column, bits beyond `width` are ignored
+fn calc_cols(lines: &[String], width: usize) -> Vec<i32> {
+    let mut cols = vec![0; width];
 
     lines.iter().for_each(|l| {
-        l.chars().enumerate().for_each(|(i, c)| match c {
-            '1' => cols[i] += 1,
-            '0' => {}
-            _ => panic!("unknown bit"),
-        })
+        l.chars()
+            .zip(cols.iter_mut())
+            .filter(|(c, _)| *c == '1')
+            .for_each(|(_, col)| *col += 1)
     });
 
     cols
 }
 
-fn power_consumption(lines: &[String]) -> i32 {
-    let cols = calc_cols(lines);
+/// Gamma and epsilon rate, errors on empty, ragged or non binary input
+pub fn gamma_epsilon(lines: &[String]) -> Result<(i32, i32)> {
+    let width = validate_width(lines)?;
+    let cols = calc_cols(lines, width);
 
-    let (gamma, epsilon) =
-        cols.iter()
-            .rev()
-            .enumerate()
-            .fold((0, 0), |(mut gamma, mut epsilon), (i, v)| {
-                let d = *v as f32 / lines.len() as f32;
+    let rates = cols
+        .iter()
+        .rev()
+        .enumerate()
+        .fold((0, 0), |(mut gamma, mut epsilon), (i, v)| {
+            let d = *v as f32 / lines.len() as f32;
 
-                let mask = 1 << i;
+            let mask = 1 << i;
 
-                if d >= 0.5 {
-                    gamma |= mask;
-                } else {
-                    epsilon |= mask;
-                }
+            if d >= 0.5 {
+                gamma |= mask;
+            } else {
+                epsilon |= mask;
+            }
 
-                (gamma, epsilon)
-            });
+            (gamma, epsilon)
+        });
 
-    gamma * epsilon
+    Ok(rates)
 }
 
-fn life_system_rating(lines: &[String], co2: bool) -> Result<i32> {
+fn power_consumption(lines: &[String]) -> Result<i32> {
+    let (gamma, epsilon) = gamma_epsilon(lines)?;
+
+    Ok(gamma * epsilon)
+}
+
+/// Remaining candidates after each bit filter round, errors on empty or ragged input
+pub fn life_rating_trace(lines: &[String], co2: bool) -> Result<Vec<Vec<i32>>> {
+    let num_bits = validate_width(lines)?;
+
     let all_numbers: Vec<i32> = lines
         .iter()
         .map(|s| i32::from_binary_str(s))
         .collect::<Result<Vec<i32>>>()?;
 
-    let num_bits = lines[0].len();
-
-    let left = (0..num_bits).scan(all_numbers, |left, bit| {
+    let trace = (0..num_bits).scan(all_numbers, |left, bit| {
         if left.len() <= 1 {
             return None;
         }
 
         let bits_set = left.iter().filter(|v| v.is_bit_set(bit, num_bits)).count();
 
-        let d = bits_set as f32 / left.len() as f32;
-
+        // on a tie oxygen keeps the ones and co2 the zeros
         let is_set_filter = match co2 {
-            true => d < 0.5,
-            false => d >= 0.5,
+            true => 2 * bits_set < left.len(),
+            false => 2 * bits_set >= left.len(),
         };
 
         let remaining: Vec<i32> = left
             .iter()
             .filter(|v| v.is_bit_set(bit, num_bits) == is_set_filter)
-            .map(|v| *v)
+            .copied()
             .collect();
 
         *left = remaining;
@@ -95,29 +139,69 @@ fn life_system_rating(lines: &[String], co2: bool) -> Result<i32> {
         Some(left.clone())
     });
 
-    match left.last() {
-        Some(last) => last.get(0).map(|x| *x).ok_or(anyhow!("not last value")),
+    Ok(trace.collect())
+}
+
+fn life_system_rating(lines: &[String], co2: bool) -> Result<i32> {
+    let trace = life_rating_trace(lines, co2)?;
+
+    match trace.last() {
+        Some(last) => last.first().copied().ok_or(anyhow!("not last value")),
         None => bail!("no last value (iterator)"),
     }
 }
 
-fn main() -> Result<()> {
-    let lines: Vec<String> = std::fs::read_to_string("input.txt")?
-        .lines()
-        .map(|l| l.to_owned())
+/// Rating together with the bit positions where exactly half of the candidates had the bit set
+pub fn life_rating_with_ties(lines: &[String], co2: bool) -> Result<(i32, Vec<usize>)> {
+    let num_bits = validate_width(lines)?;
+
+    let all_numbers: Vec<i32> = lines
+        .iter()
+        .map(|s| i32::from_binary_str(s))
+        .collect::<Result<Vec<i32>>>()?;
+    let trace = life_rating_trace(lines, co2)?;
+
+    let ties = std::iter::once(&all_numbers)
+        .chain(trace.iter())
+        .take(trace.len())
+        .enumerate()
+        .filter(|(bit, candidates)| {
+            let bits_set = candidates
+                .iter()
+                .filter(|v| v.is_bit_set(*bit, num_bits))
+                .count();
+
+            bits_set * 2 == candidates.len()
+        })
+        .map(|(bit, _)| bit)
         .collect();
 
-    let power = power_consumption(&lines);
+    let rating = trace
+        .last()
+        .and_then(|last| last.first().copied())
+        .ok_or(anyhow!("no last value"))?;
 
-    println!("Power Consumption: {}", power);
+    Ok((rating, ties))
+}
 
-    let oxygen = life_system_rating(&lines, false)?;
-    println!("oxygen: {:?}", oxygen);
+/// Power consumption and life support rating of a diagnostic report
+pub fn solve(input: &str) -> Result<(i32, i32)> {
+    let lines: Vec<String> = input.lines().map(|l| l.trim().to_owned()).collect();
 
+    let power = power_consumption(&lines)?;
+
+    let oxygen = life_system_rating(&lines, false)?;
     let co2 = life_system_rating(&lines, true)?;
-    println!("co2: {:?}", co2);
 
-    let life_support_rating = co2 * oxygen;
+    Ok((power, oxygen * co2))
+}
+
+fn main() -> Result<()> {
+    let input = std::fs::read_to_string("input.txt")?;
+
+    let (power, life_support_rating) = solve(&input)?;
+
+    println!("Power Consumption: {}", power);
     println!("life_support_rating: {:?}", life_support_rating);
 
     Ok(())
@@ -143,13 +227,42 @@ mod tests {
         let first_bit = parsed.is_bit_set(0, 12);
         let second_bit = parsed.is_bit_set(1, 12);
 
-        assert_eq!(first_bit, false);
-        assert_eq!(second_bit, true);
+        assert!(!first_bit);
+        assert!(second_bit);
     }
 
     #[test]
-    fn life_support() {
-        let input = r#"00100
+    fn unsigned_wide_values() {
+        let bs = "10000000000000000001";
+        let parsed = u32::from_binary_str(bs).unwrap();
+
+        assert_eq!(parsed, (1 << 19) + 1);
+        assert!(parsed.is_bit_set(0, 20));
+        assert!(!parsed.is_bit_set(1, 20));
+        assert!(parsed.is_bit_set(19, 20));
+
+        let wide = u64::from_binary_str(&"1".repeat(40)).unwrap();
+        assert!(wide.is_bit_set(39, 40));
+        assert!(i32::from_binary_str(&"1".repeat(40)).is_err());
+    }
+
+    #[test]
+    fn is_bit_set_narrow_width() {
+        let parsed = i32::from_binary_str("10110").unwrap();
+
+        assert!(parsed.is_bit_set(0, 5));
+        assert!(!parsed.is_bit_set(4, 5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn is_bit_set_out_of_range() {
+        let parsed = i32::from_binary_str("10110").unwrap();
+
+        parsed.is_bit_set(5, 5);
+    }
+
+    static SAMPLE_REPORT: &str = r#"00100
         11110
         10110
         10111
@@ -162,7 +275,9 @@ mod tests {
         00010
         01010"#;
 
-        let lines: Vec<String> = input.lines().map(|l| l.trim().to_owned()).collect();
+    #[test]
+    fn life_support() {
+        let lines: Vec<String> = SAMPLE_REPORT.lines().map(|l| l.trim().to_owned()).collect();
 
         let oxygen = life_system_rating(&lines, false).unwrap();
         assert_eq!(oxygen, 23);
@@ -172,4 +287,103 @@ mod tests {
 
         assert_eq!(oxygen * co2, 230)
     }
+
+    #[test]
+    fn life_rating_trace_shrinks() {
+        let lines: Vec<String> = SAMPLE_REPORT.lines().map(|l| l.trim().to_owned()).collect();
+
+        let count = |trace: Vec<Vec<i32>>| trace.iter().map(|t| t.len()).collect::<Vec<_>>();
+
+        let oxygen = life_rating_trace(&lines, false).unwrap();
+        assert_eq!(count(oxygen), vec![7, 4, 3, 2, 1]);
+
+        let co2 = life_rating_trace(&lines, true).unwrap();
+        assert_eq!(co2.last(), Some(&vec![10]));
+        assert_eq!(count(co2), vec![5, 2, 1]);
+    }
+
+    #[test]
+    fn life_rating_reports_ties() {
+        let lines: Vec<String> = ["110", "101", "011", "000", "111"]
+            .into_iter()
+            .map(|l| l.to_owned())
+            .collect();
+
+        assert_eq!(life_rating_with_ties(&lines, false).unwrap(), (7, vec![2]));
+        assert_eq!(life_rating_with_ties(&lines, true).unwrap(), (0, vec![1]));
+    }
+
+    #[test]
+    fn life_rating_empty_input() {
+        assert!(life_rating_trace(&[], false).is_err());
+        assert!(life_rating_with_ties(&[], true).is_err());
+        assert!(life_system_rating(&["".to_owned()], false).is_err());
+        assert!(solve("").is_err());
+    }
+
+    #[test]
+    fn validate_width_ragged() {
+        let lines: Vec<String> = ["10110", "0111", "10101"]
+            .into_iter()
+            .map(|l| l.to_owned())
+            .collect();
+
+        let err = validate_width(&lines).unwrap_err().to_string();
+        assert!(err.starts_with("line 2:"), "{}", err);
+    }
+
+    #[test]
+    fn validate_width_non_binary() {
+        let lines: Vec<String> = ["10110", "01111", "10201"]
+            .into_iter()
+            .map(|l| l.to_owned())
+            .collect();
+
+        let err = validate_width(&lines).unwrap_err().to_string();
+        assert!(err.starts_with("line 3:"), "{}", err);
+        assert!(err.contains("'2'"), "{}", err);
+    }
+
+    #[test]
+    fn power_consumption_sample() {
+        let lines: Vec<String> = SAMPLE_REPORT.lines().map(|l| l.trim().to_owned()).collect();
+
+        assert_eq!(validate_width(&lines).unwrap(), 5);
+        assert_eq!(power_consumption(&lines).unwrap(), 198);
+    }
+
+    #[test]
+    fn gamma_epsilon_sample() {
+        let lines: Vec<String> = SAMPLE_REPORT.lines().map(|l| l.trim().to_owned()).collect();
+
+        assert_eq!(gamma_epsilon(&lines).unwrap(), (22, 9));
+    }
+
+    #[test]
+    fn gamma_epsilon_invalid_lines() {
+        let lines: Vec<String> = ["101", "10110", "1x1"]
+            .into_iter()
+            .map(|l| l.to_owned())
+            .collect();
+
+        assert!(gamma_epsilon(&lines).is_err());
+        assert!(gamma_epsilon(&[]).is_err());
+        assert_eq!(calc_cols(&lines, 3), vec![3, 0, 3]);
+    }
+
+    #[test]
+    fn solve_sample() {
+        assert_eq!(solve(SAMPLE_REPORT).unwrap(), (198, 230));
+    }
+
+    #[test]
+    fn life_rating_exact_tie() {
+        let lines: Vec<String> = ["10", "01", "11", "00"]
+            .into_iter()
+            .map(|l| l.to_owned())
+            .collect();
+
+        assert_eq!(life_system_rating(&lines, false).unwrap(), 0b11);
+        assert_eq!(life_system_rating(&lines, true).unwrap(), 0b00);
+    }
 }
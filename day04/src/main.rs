@@ -12,6 +12,14 @@ pub type Counter = HashMap<PosIdx, usize>;
 
 pub type Pos = (RowPos, ColPos);
 
+/// Line which completed a bingo, diagonal 0 is the main and 1 the anti-diagonal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinKind {
+    Row(RowPos),
+    Col(ColPos),
+    Diag(usize),
+}
+
 #[derive(Clone, Debug)]
 pub struct BingoBoard {
     pub dim: usize,
@@ -21,11 +29,15 @@ pub struct BingoBoard {
     pub row_counter: Counter,
     pub col_counter: Counter,
 
+    /// Whether the two main diagonals count as winning lines
+    pub diagonals: bool,
+    pub diag_counter: [usize; 2],
+
     pub marked: HashSet<u8>,
 }
 
 impl BingoBoard {
-    pub fn create(fields: &Vec<Vec<u8>>) -> Result<Self> {
+    pub fn create(fields: &[Vec<u8>]) -> Result<Self> {
         let mut board = HashMap::new();
         let row_counter = HashMap::new();
         let col_counter = HashMap::new();
@@ -33,16 +45,27 @@ impl BingoBoard {
 
         let dim = fields.len();
 
+        if dim == 0 {
+            bail!("board has no rows");
+        }
+
         fields.iter().enumerate().try_for_each(|(row_idx, row)| {
             if row.len() != dim {
                 bail!("row {} has wrong length {} not {}", row_idx, row.len(), dim);
             }
 
-            row.iter().enumerate().for_each(|(col_dix, value)| {
-                board.insert(*value, (row_idx, col_dix));
-            });
-
-            Ok(())
+            row.iter().enumerate().try_for_each(|(col_dix, value)| {
+                if let Some(first) = board.insert(*value, (row_idx, col_dix)) {
+                    bail!(
+                        "duplicate value {} at {:?} and {:?}",
+                        value,
+                        first,
+                        (row_idx, col_dix)
+                    );
+                }
+
+                Ok(())
+            })
         })?;
 
         Ok(BingoBoard {
@@ -50,10 +73,23 @@ impl BingoBoard {
             dim,
             col_counter,
             row_counter,
+            diagonals: false,
+            diag_counter: [0; 2],
             marked,
         })
     }
 
+    /// Also win on a filled main diagonal or anti-diagonal
+    pub fn with_diagonals(mut self) -> Self {
+        self.diagonals = true;
+        self
+    }
+
+    /// Build a square board from its rows without going through string parsing
+    pub fn from_grid(rows: Vec<Vec<u8>>) -> Result<Self> {
+        BingoBoard::create(&rows)
+    }
+
     pub fn unmarked_sum(&self) -> usize {
         self.board
             .keys()
@@ -62,34 +98,62 @@ impl BingoBoard {
             .sum()
     }
 
-    pub fn mark(&mut self, v: u8) -> bool {
-        let pos = self.board.get(&v);
-
-        if pos.is_none() {
-            return false;
-        }
+    pub fn mark(&mut self, v: u8) -> Option<WinKind> {
+        let (row_idx, col_idx) = self.board.get(&v)?;
 
         self.marked.insert(v);
 
-        let (row_idx, col_idx) = pos.unwrap();
-
         let row_counter = self.row_counter.entry(*row_idx).or_insert(0);
         *row_counter += 1;
 
         let col_counter = self.col_counter.entry(*col_idx).or_insert(0);
         *col_counter += 1;
 
-        if *row_counter == self.dim || *col_counter == self.dim {
-            return true;
+        let line_won = if *row_counter == self.dim {
+            Some(WinKind::Row(*row_idx))
+        } else if *col_counter == self.dim {
+            Some(WinKind::Col(*col_idx))
+        } else {
+            None
+        };
+
+        let on_diagonal = [row_idx == col_idx, row_idx + col_idx == self.dim - 1];
+
+        if self.diagonals {
+            self.diag_counter
+                .iter_mut()
+                .zip(on_diagonal)
+                .filter(|(_, on)| *on)
+                .for_each(|(counter, _)| *counter += 1);
         }
 
-        false
+        line_won.or_else(|| {
+            (0..2)
+                .find(|d| self.diagonals && on_diagonal[*d] && self.diag_counter[*d] == self.dim)
+                .map(WinKind::Diag)
+        })
+    }
+
+    /// Highest fraction of marked fields over all rows and columns,
+    /// and over both diagonals when `with_diagonals` is enabled
+    pub fn closest_line(&self) -> f32 {
+        let best = self
+            .row_counter
+            .values()
+            .chain(self.col_counter.values())
+            .chain(self.diag_counter.iter())
+            .max()
+            .copied()
+            .unwrap_or(0);
+
+        best as f32 / self.dim as f32
     }
 
-    pub fn mark_all(&mut self, values: &[u8]) -> Option<(usize, usize)> {
+    /// Draw index, score and winning line of the first bingo
+    pub fn mark_all(&mut self, values: &[u8]) -> Option<(usize, usize, WinKind)> {
         for (i, v) in values.iter().enumerate() {
-            if self.mark(*v) {
-                return Some((i, *v as usize * self.unmarked_sum()));
+            if let Some(kind) = self.mark(*v) {
+                return Some((i, *v as usize * self.unmarked_sum(), kind));
             }
         }
 
@@ -97,6 +161,37 @@ impl BingoBoard {
     }
 }
 
+/// Grid with every value right-aligned and marked values in brackets, e.g. `[42]`
+impl std::fmt::Display for BingoBoard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .board
+            .keys()
+            .map(|v| v.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        let mut grid = vec![vec![0; self.dim]; self.dim];
+        self.board
+            .iter()
+            .for_each(|(value, (row_idx, col_idx))| grid[*row_idx][*col_idx] = *value);
+
+        let rows: Vec<String> = grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|v| match self.marked.contains(v) {
+                        true => format!("[{:>width$}]", v, width = width),
+                        false => format!(" {:>width$} ", v, width = width),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
 impl FromStr for BingoBoard {
     type Err = Error;
 
@@ -105,20 +200,115 @@ impl FromStr for BingoBoard {
             .lines()
             .map(|l| {
                 l.split(" ")
-                    .filter(|s| *s != "")
+                    .filter(|s| !s.is_empty())
                     .map(|v| v.trim().parse::<u8>().map_err(|e| anyhow!(e)))
                     .collect::<Result<Vec<u8>>>()
             })
             .collect::<Result<Vec<Vec<u8>>>>()?;
 
-        BingoBoard::create(&fields)
+        BingoBoard::from_grid(fields)
+    }
+}
+
+/// Number of marks (draws not on the board ignored) until the board wins
+pub fn marks_to_win(board: &BingoBoard, drawn: &[u8]) -> Option<usize> {
+    let mut board = board.clone();
+
+    let on_board: Vec<u8> = drawn
+        .iter()
+        .filter(|v| board.board.contains_key(v))
+        .copied()
+        .collect();
+
+    on_board
+        .into_iter()
+        .position(|v| board.mark(v).is_some())
+        .map(|i| i + 1)
+}
+
+pub fn win_order(drawn: &[u8], boards: Vec<BingoBoard>) -> Vec<(usize, usize)> {
+    let mut winners: Vec<(usize, usize, usize)> = boards
+        .into_iter()
+        .enumerate()
+        .filter_map(|(board_idx, mut b)| {
+            b.mark_all(drawn)
+                .map(|(draw_idx, score, _)| (draw_idx, board_idx, score))
+        })
+        .collect();
+
+    winners.sort_by_key(|(draw_idx, _, _)| *draw_idx);
+
+    winners
+        .into_iter()
+        .map(|(_, board_idx, score)| (board_idx, score))
+        .collect()
+}
+
+/// Mark each draw as it arrives, calling `on_win` with board index, draw and score once per board
+pub fn play_stream(
+    draws: impl Iterator<Item = u8>,
+    boards: &mut [BingoBoard],
+    mut on_win: impl FnMut(usize, u8, usize),
+) {
+    let mut won = vec![false; boards.len()];
+
+    for draw in draws {
+        boards
+            .iter_mut()
+            .zip(won.iter_mut())
+            .enumerate()
+            .filter(|(_, (_, won))| !**won)
+            .for_each(|(idx, (board, won))| {
+                if board.mark(draw).is_some() {
+                    *won = true;
+                    on_win(idx, draw, draw as usize * board.unmarked_sum());
+                }
+            });
+
+        if won.iter().all(|w| *w) {
+            break;
+        }
+    }
+}
+
+/// Draw index of every board's first win, boards stop being marked once they won
+pub fn play(boards: &mut [BingoBoard], draws: &[u8]) -> Vec<Option<usize>> {
+    let mut won_at = vec![None; boards.len()];
+
+    for (draw_idx, draw) in draws.iter().enumerate() {
+        boards
+            .iter_mut()
+            .zip(won_at.iter_mut())
+            .filter(|(_, won_at)| won_at.is_none())
+            .for_each(|(board, won_at)| {
+                if board.mark(*draw).is_some() {
+                    *won_at = Some(draw_idx);
+                }
+            });
+
+        if won_at.iter().all(|w| w.is_some()) {
+            break;
+        }
     }
+
+    won_at
+}
+
+/// Indices of the boards which never complete a line with the drawn numbers
+pub fn never_winning(drawn: &[u8], boards: &[BingoBoard]) -> Vec<usize> {
+    boards
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| marks_to_win(b, drawn).is_none())
+        .map(|(idx, _)| idx)
+        .collect()
 }
 
-fn load_input(path: &str) -> Result<(Vec<u8>, Vec<BingoBoard>)> {
-    let raw = std::fs::read_to_string(path)?;
+fn parse_input(raw: &str) -> Result<(Vec<u8>, Vec<BingoBoard>)> {
+    let normalized = raw.replace("\r\n", "\n");
+    let mut lines = normalized.trim().lines();
 
-    let drawn_raw: String = raw.lines().take(1).collect();
+    let drawn_raw = lines.next().unwrap_or_default();
 
     let drawn = drawn_raw
         .split(",")
@@ -126,38 +316,273 @@ fn load_input(path: &str) -> Result<(Vec<u8>, Vec<BingoBoard>)> {
         .collect::<Result<Vec<u8>>>()
         .context("Parsing Drawn")?;
 
-    let boards: Vec<BingoBoard> = raw
-        .split("\n\n")
-        .skip(1)
-        .map(|s| BingoBoard::from_str(s))
+    let board_lines: Vec<&str> = lines.collect();
+
+    let boards: Vec<BingoBoard> = board_lines
+        .split(|l| l.trim().is_empty())
+        .filter(|chunk| !chunk.is_empty())
+        .map(|chunk| BingoBoard::from_str(&chunk.join("\n")))
         .collect::<Result<Vec<BingoBoard>>>()
         .context("Parsing Boards")?;
 
+    if boards.is_empty() {
+        bail!("no boards found after the drawn numbers");
+    }
+
     Ok((drawn, boards))
 }
 
-fn main() -> Result<()> {
-    let (drawn, boards) = load_input("input.txt")?;
+/// Scores of the first and the last winning board
+pub fn solve(raw: &str) -> Result<(usize, usize)> {
+    let (drawn, mut boards) = parse_input(raw)?;
 
-    let boards_winner: Vec<(usize, usize)> = boards
-        .into_iter()
-        .map(|mut b| b.mark_all(&drawn))
-        .filter_map(|b| b)
+    let won_at = play(&mut boards, &drawn);
+
+    let scores: Vec<(usize, usize)> = won_at
+        .iter()
+        .zip(boards.iter())
+        .filter_map(|(won_at, board)| {
+            won_at.map(|draw_idx| (draw_idx, drawn[draw_idx] as usize * board.unmarked_sum()))
+        })
         .collect();
 
-    let (first_values, first_score) = boards_winner
+    let (_, first_score) = scores
         .iter()
-        .min_by(|(x_draw, _), (y_draw, _)| x_draw.cmp(y_draw))
+        .min_by_key(|(draw_idx, _)| *draw_idx)
         .ok_or(anyhow!("no first"))?;
-
-    println!("Part1: {} ({})", first_score, first_values);
-
-    let (last_values, last_score) = boards_winner
+    let (_, last_score) = scores
         .iter()
-        .max_by(|(x_draw, _), (y_draw, _)| x_draw.cmp(y_draw))
+        .max_by_key(|(draw_idx, _)| *draw_idx)
         .ok_or(anyhow!("no last"))?;
 
-    println!("Part2: {} ({})", last_score, last_values);
+    Ok((*first_score, *last_score))
+}
+
+fn main() -> Result<()> {
+    let raw = std::fs::read_to_string("input.txt")?;
+
+    let (first_score, last_score) = solve(&raw)?;
+
+    println!("Part1: {}", first_score);
+    println!("Part2: {}", last_score);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static SAMPLE_INPUT: &str = r"7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+
+22 13 17 11  0
+ 8  2 23  4 24
+21  9 14 16  7
+ 6 10  3 18  5
+ 1 12 20 15 19
+
+ 3 15  0  2 22
+ 9 18 13 17  5
+19  8  7 25 23
+20 11 10 24  4
+14 21 16 12  6
+
+14 21 17 24  4
+10 16 15  9 19
+18  8 23 26 20
+22 11 13  6  5
+ 2  0 12  3  7";
+
+    #[test]
+    fn win_order_sample() {
+        let (drawn, boards) = parse_input(SAMPLE_INPUT).unwrap();
+
+        let order = win_order(&drawn, boards);
+
+        assert_eq!(order.len(), 3);
+        assert_eq!(order.first(), Some(&(2, 4512)));
+        assert_eq!(order.last(), Some(&(1, 1924)));
+    }
+
+    #[test]
+    fn marks_to_win_sample() {
+        let (drawn, boards) = parse_input(SAMPLE_INPUT).unwrap();
+
+        assert_eq!(marks_to_win(&boards[2], &drawn), Some(12));
+        assert_eq!(marks_to_win(&boards[2], &drawn[..11]), None);
+    }
+
+    #[test]
+    fn from_grid_mark() {
+        let mut board =
+            BingoBoard::from_grid(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]).unwrap();
+
+        assert_eq!(board.mark(4), None);
+        assert_eq!(board.mark(5), None);
+        assert!(board.mark(6).is_some());
+        assert_eq!(board.unmarked_sum(), 1 + 2 + 3 + 7 + 8 + 9);
+    }
+
+    #[test]
+    fn from_grid_invalid() {
+        assert!(BingoBoard::from_grid(vec![]).is_err());
+        assert!(BingoBoard::from_grid(vec![vec![1, 2], vec![3]]).is_err());
+        assert!(BingoBoard::from_grid(vec![vec![1, 2, 3], vec![4, 5, 6]]).is_err());
+        assert!(BingoBoard::from_grid(vec![vec![1, 2], vec![2, 3]]).is_err());
+    }
+
+    #[test]
+    fn duplicate_value_rejected() {
+        let raw = "1 2 7\n4 5 6\n7 8 9";
+
+        let err = BingoBoard::from_str(raw).unwrap_err().to_string();
+        assert_eq!(err, "duplicate value 7 at (0, 2) and (2, 0)");
+    }
+
+    #[test]
+    fn never_winning_board() {
+        let (drawn, mut boards) = parse_input(SAMPLE_INPUT).unwrap();
+        assert!(never_winning(&drawn, &boards).is_empty());
+
+        let loser = BingoBoard::from_grid(vec![vec![7, 4, 90], vec![91, 92, 93], vec![9, 94, 5]]);
+        boards.push(loser.unwrap());
+
+        assert_eq!(never_winning(&drawn, &boards), vec![3]);
+    }
+
+    #[test]
+    fn closest_line_ratio() {
+        let mut board =
+            BingoBoard::from_grid(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]).unwrap();
+        assert_eq!(board.closest_line(), 0.0);
+
+        board.mark(4);
+        board.mark(6);
+        board.mark(9);
+        assert!((board.closest_line() - 2.0 / 3.0).abs() < f32::EPSILON);
+
+        board.mark(5);
+        assert_eq!(board.closest_line(), 1.0);
+    }
+
+    #[test]
+    fn closest_line_diagonals() {
+        let grid = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+        let mut plain = BingoBoard::from_grid(grid.clone()).unwrap();
+        let mut diagonal = BingoBoard::from_grid(grid).unwrap().with_diagonals();
+
+        for v in [1, 5] {
+            plain.mark(v);
+            diagonal.mark(v);
+        }
+
+        assert!((plain.closest_line() - 1.0 / 3.0).abs() < f32::EPSILON);
+        assert!((diagonal.closest_line() - 2.0 / 3.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn play_stream_sample() {
+        let (drawn, mut boards) = parse_input(SAMPLE_INPUT).unwrap();
+
+        let mut wins = Vec::new();
+        play_stream(drawn.into_iter(), &mut boards, |board, draw, score| {
+            wins.push((board, draw, score))
+        });
+
+        assert_eq!(wins.len(), 3);
+        assert_eq!(wins[0], (2, 24, 4512));
+        assert_eq!(wins[2], (1, 13, 1924));
+        assert_eq!(wins[1].0, 0);
+    }
+
+    #[test]
+    fn solve_sample() {
+        assert_eq!(solve(SAMPLE_INPUT).unwrap(), (4512, 1924));
+    }
+
+    #[test]
+    fn diagonal_wins() {
+        let grid = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+
+        let mut default = BingoBoard::from_grid(grid.clone()).unwrap();
+        assert_eq!(default.mark(1), None);
+        assert_eq!(default.mark(5), None);
+        assert_eq!(default.mark(9), None);
+
+        let mut main = BingoBoard::from_grid(grid.clone())
+            .unwrap()
+            .with_diagonals();
+        assert_eq!(main.mark(1), None);
+        assert_eq!(main.mark(5), None);
+        assert!(main.mark(9).is_some());
+
+        let mut anti = BingoBoard::from_grid(grid).unwrap().with_diagonals();
+        assert_eq!(anti.mark(3), None);
+        assert_eq!(anti.mark(5), None);
+        assert!(anti.mark(7).is_some());
+
+        // a full diagonal only wins for the draw which completed it
+        assert_eq!(anti.mark(2), None);
+        assert_eq!(main.mark(8), None);
+    }
+
+    #[test]
+    fn win_kind_row_and_col() {
+        let grid = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+
+        let mut board = BingoBoard::from_grid(grid.clone()).unwrap();
+        assert_eq!(
+            board.mark_all(&[4, 2, 5, 6]),
+            Some((3, 6 * 28, WinKind::Row(1)))
+        );
+
+        let mut board = BingoBoard::from_grid(grid.clone()).unwrap();
+        assert_eq!(board.mark(3), None);
+        assert_eq!(board.mark(9), None);
+        assert_eq!(board.mark(6), Some(WinKind::Col(2)));
+
+        let mut board = BingoBoard::from_grid(grid).unwrap().with_diagonals();
+        assert_eq!(
+            board.mark_all(&[3, 5, 7]).map(|(_, _, k)| k),
+            Some(WinKind::Diag(1))
+        );
+    }
+
+    #[test]
+    fn display_marks() {
+        let mut board =
+            BingoBoard::from_grid(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 42]]).unwrap();
+
+        board.mark(42);
+        board.mark(5);
+
+        let rendered = board.to_string();
+        assert!(rendered.contains("[42]"));
+        assert!(rendered.contains("[ 5]"));
+        assert_eq!(rendered.lines().next(), Some("  1   2   3 "));
+        assert_eq!(rendered.lines().count(), 3);
+    }
+
+    #[test]
+    fn play_sample() {
+        let (drawn, mut boards) = parse_input(SAMPLE_INPUT).unwrap();
+
+        let won_at = play(&mut boards, &drawn);
+
+        assert_eq!(won_at, vec![Some(13), Some(14), Some(11)]);
+        assert_eq!(drawn[11] as usize * boards[2].unmarked_sum(), 4512);
+        assert_eq!(drawn[14] as usize * boards[1].unmarked_sum(), 1924);
+    }
+
+    #[test]
+    fn parse_input_crlf() {
+        let crlf = format!("\r\n\r\n{}\r\n\r\n", SAMPLE_INPUT.replace('\n', "\r\n"));
+
+        let (drawn, boards) = parse_input(&crlf).unwrap();
+        assert_eq!(drawn.len(), 27);
+        assert_eq!(boards.len(), 3);
+
+        assert_eq!(solve(&crlf).unwrap(), (4512, 1924));
+        assert!(parse_input("7,4,9\r\n\r\n").is_err());
+    }
+}
@@ -1,28 +1,46 @@
-use anyhow::{anyhow, Error, Result};
-use std::{cmp::Ordering, collections::HashMap, str::FromStr};
+use anyhow::{anyhow, bail, Error, Result};
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    str::FromStr,
+};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
-pub struct Point {
-    pub x: i32,
-    pub y: i32,
+pub struct Point<T = i32> {
+    pub x: T,
+    pub y: T,
 }
 
-impl FromStr for Point {
+impl<T> FromStr for Point<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
         let split: Vec<&str> = s.split(",").collect();
 
-        let x_raw = *split.get(0).ok_or(anyhow!("x not found"))?;
+        let x_raw = *split.first().ok_or(anyhow!("x not found"))?;
         let y_raw = *split.get(1).ok_or(anyhow!("y not found"))?;
 
-        let x: i32 = x_raw.trim().parse()?;
-        let y: i32 = y_raw.trim().parse()?;
+        let x: T = x_raw.trim().parse()?;
+        let y: T = y_raw.trim().parse()?;
 
         Ok(Point { x, y })
     }
 }
 
+impl Point<f64> {
+    /// Snap to the nearest integer grid point
+    pub fn snap(&self) -> Point {
+        Point {
+            x: self.x.round() as i32,
+            y: self.y.round() as i32,
+        }
+    }
+}
+
 pub trait Direction {
     fn to_direction(&self) -> i32;
 }
@@ -56,6 +74,107 @@ impl Line {
     pub fn points(&self) -> LineIntoIterator {
         self.into_iter()
     }
+
+    /// Snap fractional endpoints to the grid, the result has to be straight or diagonal
+    pub fn from_float(start: Point<f64>, end: Point<f64>) -> Result<Line> {
+        let line = Line {
+            start: start.snap(),
+            end: end.snap(),
+        };
+
+        let dx = (line.end.x - line.start.x).abs();
+        let dy = (line.end.y - line.start.y).abs();
+
+        if dx != 0 && dy != 0 && dx != dy {
+            bail!("snapped line is neither straight nor diagonal: {:?}", line);
+        }
+
+        Ok(line)
+    }
+
+    fn direction(&self) -> (i32, i32) {
+        self.start.directions(&self.end)
+    }
+
+    /// Number of steps from start to end
+    fn steps(&self) -> i32 {
+        (self.end.x - self.start.x)
+            .abs()
+            .max((self.end.y - self.start.y).abs())
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        let (dx, dy) = self.direction();
+        let (rx, ry) = (point.x - self.start.x, point.y - self.start.y);
+
+        if rx * dy - ry * dx != 0 {
+            return false;
+        }
+
+        if (dx, dy) == (0, 0) {
+            return (rx, ry) == (0, 0);
+        }
+
+        let t = (rx * dx + ry * dy) / (dx * dx + dy * dy);
+        (0..=self.steps()).contains(&t)
+    }
+
+    /// First grid point of `self` which is also part of `other`
+    pub fn intersection(&self, other: &Line) -> Option<Point> {
+        let (dx1, dy1) = self.direction();
+        let (dx2, dy2) = other.direction();
+
+        if (dx1, dy1) == (0, 0) {
+            return other.contains(&self.start).then_some(self.start);
+        }
+        if (dx2, dy2) == (0, 0) {
+            return self.contains(&other.start).then_some(other.start);
+        }
+
+        let at = |t: i32| Point {
+            x: self.start.x + t * dx1,
+            y: self.start.y + t * dy1,
+        };
+
+        let (rx, ry) = (other.start.x - self.start.x, other.start.y - self.start.y);
+        let det = dx2 * dy1 - dx1 * dy2;
+
+        if det == 0 {
+            // parallel, only collinear lines can share points
+            if rx * dy1 - ry * dx1 != 0 {
+                return None;
+            }
+
+            let norm = dx1 * dx1 + dy1 * dy1;
+            let project =
+                |p: &Point| ((p.x - self.start.x) * dx1 + (p.y - self.start.y) * dy1) / norm;
+
+            let (a, b) = (project(&other.start), project(&other.end));
+            let from = a.min(b).max(0);
+            let to = a.max(b).min(self.steps());
+
+            return (from <= to).then(|| at(from));
+        }
+
+        let t_num = dx2 * ry - dy2 * rx;
+        let u_num = dx1 * ry - dy1 * rx;
+
+        // diagonals may cross between grid points
+        if t_num % det != 0 || u_num % det != 0 {
+            return None;
+        }
+
+        let (t, u) = (t_num / det, u_num / det);
+
+        ((0..=self.steps()).contains(&t) && (0..=other.steps()).contains(&u)).then(|| at(t))
+    }
+
+    pub fn reversed(&self) -> Line {
+        Line {
+            start: self.end,
+            end: self.start,
+        }
+    }
 }
 
 impl FromStr for Line {
@@ -64,7 +183,7 @@ impl FromStr for Line {
     fn from_str(s: &str) -> Result<Self> {
         let split: Vec<&str> = s.split(" -> ").collect();
 
-        let start_raw = *split.get(0).ok_or(anyhow!("start not found"))?;
+        let start_raw = *split.first().ok_or(anyhow!("start not found"))?;
         let end_raw = *split.get(1).ok_or(anyhow!("end not found"))?;
 
         let start = Point::from_str(start_raw.trim())?;
@@ -130,16 +249,143 @@ impl Iterator for LineIntoIterator {
 fn load_lines(path: &str) -> Result<Vec<Line>> {
     std::fs::read_to_string(path)?
         .lines()
-        .map(|l| Line::from_str(l))
+        .map(Line::from_str)
         .collect()
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diagram {
+    pub points: HashMap<Point, usize>,
+}
+
+impl Diagram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_line(&mut self, line: Line) {
+        self.add_line_delta(line);
+    }
+
+    /// Add the line and return the number of points which became overlaps
+    pub fn add_line_delta(&mut self, line: Line) -> usize {
+        line.points()
+            .filter(|p| {
+                let count = self.points.entry(*p).or_insert(0);
+                *count += 1;
+
+                *count == 2
+            })
+            .count()
+    }
+
+    pub fn overlaps(&self) -> usize {
+        num_overlaps(&self.points)
+    }
+
+    pub fn remove_line(&mut self, line: Line) {
+        line.points().for_each(|p| {
+            if let Entry::Occupied(mut entry) = self.points.entry(p) {
+                *entry.get_mut() -= 1;
+
+                if *entry.get() == 0 {
+                    entry.remove();
+                }
+            }
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+}
+
 fn draw_lines(lines: &[Line]) -> HashMap<Point, usize> {
-    lines.iter().fold(HashMap::new(), |mut map, line| {
-        line.points().for_each(|p| *map.entry(p).or_insert(0) += 1);
+    let diagram = lines.iter().fold(Diagram::new(), |mut diagram, line| {
+        diagram.add_line(*line);
+
+        diagram
+    });
+
+    diagram.points
+}
+
+/// Number of line pairs sharing at least one point
+pub fn overlapping_pairs(lines: &[Line]) -> usize {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            lines[i + 1..]
+                .iter()
+                .filter(|other| line.intersection(other).is_some())
+                .count()
+        })
+        .sum()
+}
+
+/// Combine overlapping or adjacent segments lying on the same line into single segments
+pub fn merge_collinear(lines: &[Line]) -> Vec<Line> {
+    // direction and the position of the line the segment lies on
+    type LineKey = ((i32, i32), i32);
+
+    // segments as ranges along their line
+    let mut groups: BTreeMap<LineKey, Vec<(i32, i32)>> = BTreeMap::new();
 
-        map
-    })
+    lines.iter().for_each(|line| {
+        let (dx, dy) = line.direction();
+
+        // single points count as horizontal, everything else points right or down
+        let line = match (dx, dy) {
+            (0, 0) => *line,
+            _ if dx < 0 || (dx == 0 && dy < 0) => line.reversed(),
+            _ => *line,
+        };
+        let direction = match line.direction() {
+            (0, 0) => (1, 0),
+            d => d,
+        };
+
+        let position = line.start.x * direction.1 - line.start.y * direction.0;
+        let along = |p: &Point| if direction.0 != 0 { p.x } else { p.y };
+
+        groups
+            .entry((direction, position))
+            .or_default()
+            .push((along(&line.start), along(&line.end)));
+    });
+
+    groups
+        .into_iter()
+        .flat_map(|(((dx, dy), position), mut ranges)| {
+            ranges.sort();
+
+            let mut merged: Vec<(i32, i32)> = Vec::new();
+            ranges
+                .into_iter()
+                .for_each(|(from, to)| match merged.last_mut() {
+                    Some(last) if from <= last.1 + 1 => last.1 = last.1.max(to),
+                    _ => merged.push((from, to)),
+                });
+
+            // invert `along` and `position` to get back to points
+            let at = move |t: i32| {
+                if dx != 0 {
+                    Point {
+                        x: t,
+                        y: (t * dy - position) / dx,
+                    }
+                } else {
+                    Point { x: position, y: t }
+                }
+            };
+
+            merged.into_iter().map(move |(from, to)| Line {
+                start: at(from),
+                end: at(to),
+            })
+        })
+        .collect()
 }
 
 fn num_overlaps(diagram: &HashMap<Point, usize>) -> usize {
@@ -206,4 +452,147 @@ mod tests {
         ];
         assert_eq!(points, expected);
     }
+
+    #[test]
+    fn diagram_add_remove() {
+        let line = Line::from_str("9,7 -> 7,9").expect("line parsing failed");
+        let crossing = Line::from_str("7,7 -> 9,9").expect("line parsing failed");
+
+        let mut diagram = Diagram::new();
+        diagram.add_line(line);
+        diagram.add_line(crossing);
+        assert_eq!(diagram.points.get(&Point { x: 8, y: 8 }), Some(&2));
+
+        diagram.remove_line(line);
+        assert_eq!(diagram.points.get(&Point { x: 8, y: 8 }), Some(&1));
+
+        diagram.remove_line(crossing);
+        assert!(diagram.is_empty());
+    }
+
+    #[test]
+    fn reversed_points() {
+        ["9,7 -> 7,7", "9,7 -> 7,9", "1,1 -> 1,3", "5,5 -> 5,5"]
+            .into_iter()
+            .for_each(|raw| {
+                let line = Line::from_str(raw).expect("line parsing failed");
+
+                let mut expected: Vec<Point> = line.points().collect();
+                expected.reverse();
+
+                let reversed: Vec<Point> = line.reversed().points().collect();
+                assert_eq!(reversed, expected);
+            });
+    }
+
+    #[test]
+    fn float_line_snaps_to_grid() {
+        let start = Point::<f64>::from_str("0.2,0.6").unwrap();
+        let end = Point::<f64>::from_str("2.4,2.9").unwrap();
+
+        let line = Line::from_float(start, end).unwrap();
+        let points: Vec<Point> = line.points().collect();
+
+        let expected = vec![
+            Point { x: 0, y: 1 },
+            Point { x: 1, y: 2 },
+            Point { x: 2, y: 3 },
+        ];
+        assert_eq!(points, expected);
+
+        let skewed = Point::<f64>::from_str("2.5,1.6").unwrap();
+        assert!(Line::from_float(start, skewed).is_err());
+    }
+
+    #[test]
+    fn overlapping_pairs_small() {
+        let lines: Vec<Line> = [
+            "0,0 -> 4,4",
+            "0,4 -> 4,0",
+            "1,0 -> 5,4",
+            "6,6 -> 2,2",
+            "9,9 -> 9,9",
+        ]
+        .into_iter()
+        .map(|raw| Line::from_str(raw).unwrap())
+        .collect();
+
+        assert_eq!(lines[0].intersection(&lines[1]), Some(Point { x: 2, y: 2 }));
+        assert_eq!(lines[0].intersection(&lines[3]), Some(Point { x: 2, y: 2 }));
+        assert_eq!(lines[1].intersection(&lines[2]), None);
+        assert_eq!(overlapping_pairs(&lines), 3);
+    }
+
+    #[test]
+    fn overlapping_pairs_match_points() {
+        let lines: Vec<Line> = r"0,9 -> 5,9
+        8,0 -> 0,8
+        9,4 -> 3,4
+        2,2 -> 2,1
+        7,0 -> 7,4
+        6,4 -> 2,0
+        0,9 -> 2,9
+        3,4 -> 1,4
+        0,0 -> 8,8
+        5,5 -> 8,2"
+            .lines()
+            .map(|raw| Line::from_str(raw.trim()).unwrap())
+            .collect();
+
+        let shared = |a: &Line, b: &Line| a.points().any(|p| b.points().any(|q| p == q));
+        let expected = (0..lines.len())
+            .flat_map(|i| (i + 1..lines.len()).map(move |j| (i, j)))
+            .filter(|(i, j)| shared(&lines[*i], &lines[*j]))
+            .count();
+
+        assert_eq!(overlapping_pairs(&lines), expected);
+    }
+
+    #[test]
+    fn diagram_overlap_delta() {
+        let mut diagram = Diagram::new();
+
+        let first = Line::from_str("0,0 -> 4,4").unwrap();
+        let crossing = Line::from_str("0,4 -> 4,0").unwrap();
+        let overlapping = Line::from_str("1,1 -> 3,3").unwrap();
+
+        assert_eq!(diagram.add_line_delta(first), 0);
+        assert_eq!(diagram.add_line_delta(crossing), 1);
+        assert_eq!(diagram.overlaps(), 1);
+
+        // (2, 2) already overlaps, only (1, 1) and (3, 3) are new
+        assert_eq!(diagram.add_line_delta(overlapping), 2);
+        assert_eq!(diagram.overlaps(), 3);
+    }
+
+    #[test]
+    fn merge_collinear_segments() {
+        let lines: Vec<Line> = [
+            "0,9 -> 5,9",
+            "7,9 -> 3,9",
+            "8,9 -> 9,9",
+            "2,2 -> 2,1",
+            "0,0 -> 2,2",
+            "5,5 -> 3,3",
+            "5,3 -> 3,5",
+        ]
+        .into_iter()
+        .map(|raw| Line::from_str(raw).unwrap())
+        .collect();
+
+        let merged = merge_collinear(&lines);
+
+        assert_eq!(merged.len(), 4);
+        assert!(merged.contains(&Line::from_str("0,9 -> 9,9").unwrap()));
+        assert!(merged.contains(&Line::from_str("0,0 -> 5,5").unwrap()));
+        assert!(merged.contains(&Line::from_str("2,1 -> 2,2").unwrap()));
+        assert!(merged.contains(&Line::from_str("3,5 -> 5,3").unwrap()));
+
+        let covered = |lines: &[Line]| {
+            let mut points: Vec<Point> = draw_lines(lines).into_keys().collect();
+            points.sort_by_key(|p| (p.x, p.y));
+            points
+        };
+        assert_eq!(covered(&merged), covered(&lines));
+    }
 }
@@ -5,7 +5,7 @@ fn load_initial_population(input: &str) -> Result<[usize; 9]> {
 
     let fish: Vec<usize> = input
         .split(",")
-        .filter(|s| *s != "")
+        .filter(|s| !s.is_empty())
         .map(|s| {
             s.trim()
                 .parse::<usize>()
@@ -25,35 +25,134 @@ fn load_initial_population(input: &str) -> Result<[usize; 9]> {
     Ok(population)
 }
 
-fn next_population(start: &[usize; 9]) -> [usize; 9] {
-    let mut next = start.clone();
+/// Yields the population per age of every day, starting with day 0
+///
+/// Counts are kept as u128, the iterator ends once they no longer fit. That happens after
+/// roughly 1000 days, the sample's five fish last until day 1020.
+pub struct LanternfishSim {
+    population: Option<[u128; 9]>,
+}
+
+impl LanternfishSim {
+    pub fn new(initial: [usize; 9]) -> Self {
+        Self {
+            population: Some(initial.map(|v| v as u128)),
+        }
+    }
+}
+
+impl Iterator for LanternfishSim {
+    type Item = [u128; 9];
 
-    next.rotate_left(1);
-    next[6] += next[8];
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.population?;
 
-    next
+        let mut next = current;
+        next.rotate_left(1);
+
+        self.population = next[6].checked_add(next[8]).map(|parents| {
+            next[6] = parents;
+            next
+        });
+
+        Some(current)
+    }
 }
 
-fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
-    let initial_population = load_initial_population(&input)?;
-    println!("{:?}", initial_population);
+/// Population per age after `day` days, errors if a count does not fit into usize
+pub fn population_at(initial: &[usize; 9], day: usize) -> Result<[usize; 9]> {
+    let population = LanternfishSim::new(*initial)
+        .nth(day)
+        .ok_or(anyhow!("population overflows u128 before day {}", day))?;
 
-    let mut population_history: Vec<[usize; 9]> = Vec::new();
-    population_history.push(initial_population.clone());
+    let mut converted = [0; 9];
+    for (age, count) in population.into_iter().enumerate() {
+        converted[age] = usize::try_from(count)
+            .map_err(|_| anyhow!("population of age {} overflows usize on day {}", age, day))?;
+    }
 
-    let final_population = (0..256).into_iter().fold(initial_population, |current, _| {
-        let next = next_population(&current);
-        population_history.push(next.clone());
+    Ok(converted)
+}
+
+/// Number of fish over all ages, errors if it does not fit into usize
+pub fn total_population(population: &[usize; 9]) -> Result<usize> {
+    population
+        .iter()
+        .try_fold(0usize, |acc, count| acc.checked_add(*count))
+        .ok_or(anyhow!("total population overflows usize"))
+}
+
+/// Number of fish after 80 and after 256 days
+pub fn solve(input: &str) -> Result<(usize, usize)> {
+    let initial_population = load_initial_population(input)?;
 
-        next
-    });
+    let after_80 = total_population(&population_at(&initial_population, 80)?)?;
+    let after_256 = total_population(&population_at(&initial_population, 256)?)?;
 
-    let after_80: usize = population_history[80].iter().sum();
-    let total: usize = final_population.iter().sum();
+    Ok((after_80, after_256))
+}
+
+fn main() -> Result<()> {
+    let input = std::fs::read_to_string("input.txt")?;
+
+    let (after_80, total) = solve(&input)?;
 
     println!("Part1 : {}", after_80);
     println!("Part2 : {}", total);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn population_at_day_18() {
+        let initial = load_initial_population("3,4,3,1,2").unwrap();
+
+        let population = population_at(&initial, 18).unwrap();
+
+        assert_eq!(population, [3, 5, 3, 2, 2, 1, 5, 1, 4]);
+        assert_eq!(population.iter().sum::<usize>(), 26);
+    }
+
+    #[test]
+    fn simulator_nth_80() {
+        let initial = load_initial_population("3,4,3,1,2").unwrap();
+
+        let population = LanternfishSim::new(initial).nth(80).unwrap();
+
+        assert_eq!(population.iter().sum::<u128>(), 5934);
+    }
+
+    #[test]
+    fn large_horizons() {
+        let initial = load_initial_population("3,4,3,1,2").unwrap();
+
+        // beyond usize but still within u128
+        assert!(population_at(&initial, 900).is_err());
+        let population = LanternfishSim::new(initial).nth(900).unwrap();
+        assert!(population.iter().sum::<u128>() > usize::MAX as u128);
+
+        // even u128 runs out after about 1000 days
+        assert!(LanternfishSim::new(initial).nth(1020).is_some());
+        assert!(LanternfishSim::new(initial).nth(1021).is_none());
+        assert!(population_at(&initial, 2000).is_err());
+    }
+
+    #[test]
+    fn total_population_overflow() {
+        let mut population = [0; 9];
+        assert_eq!(total_population(&population).unwrap(), 0);
+
+        population[0] = usize::MAX;
+        population[8] = 1;
+        assert!(total_population(&population).is_err());
+    }
+
+    #[test]
+    fn solve_sample() {
+        assert_eq!(solve("3,4,3,1,2").unwrap(), (5934, 26984457539));
+    }
+}
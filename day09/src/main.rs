@@ -6,23 +6,34 @@ pub type Point = (isize, isize);
 
 pub type HightMap = HashMap<Point, u8>;
 
-pub fn load_map(input: &str) -> Result<HightMap> {
+/// Height which separates the basins
+pub const WALL: u8 = 9;
+
+fn load_map_radix(input: &str, radix: u32) -> Result<HightMap> {
     input
         .lines()
         .enumerate()
-        .map(|(i, l)| {
+        .flat_map(|(i, l)| {
             l.trim().chars().enumerate().map(move |(j, c)| {
-                let d = c
-                    .to_digit(10)
-                    .ok_or(anyhow!("invalid char '{}' in ({}, {})", c, i, j))?
-                    as u8;
+                let d =
+                    c.to_digit(radix)
+                        .ok_or(anyhow!("invalid char '{}' in ({}, {})", c, i, j))?
+                        as u8;
                 Ok(((i as isize, j as isize), d))
             })
         })
-        .flatten()
         .collect()
 }
 
+pub fn load_map(input: &str) -> Result<HightMap> {
+    load_map_radix(input, 10)
+}
+
+/// Load a map with heights 0-35 encoded as base 36 chars (`a` = 10)
+pub fn load_map_base36(input: &str) -> Result<HightMap> {
+    load_map_radix(input, 36)
+}
+
 pub fn get_neighbors(map: &HightMap, point: Point) -> Vec<(Point, u8)> {
     let (i, j) = point;
 
@@ -45,10 +56,7 @@ pub fn get_neighbors(map: &HightMap, point: Point) -> Vec<(Point, u8)> {
         (right, right_value.cloned()),
     ]
     .iter()
-    .filter_map(|(point, o)| match o {
-        Some(v) => Some((*point, *v)),
-        None => None,
-    })
+    .filter_map(|(point, o)| o.map(|v| (*point, v)))
     .collect()
 }
 
@@ -79,35 +87,158 @@ pub fn find_low_points(map: &HightMap) -> Vec<(Point, u8)> {
         .collect()
 }
 
-pub fn calc_basin_sizes(map: &HightMap, low_points: Vec<Point>) -> Vec<usize> {
-    let mut visited: HashSet<Point> = low_points.iter().map(|p| *p).collect();
+/// Map every basin cell to the index of the low point the basin belongs to
+pub fn label_basins(map: &HightMap, low_points: &[Point], wall: u8) -> HashMap<Point, usize> {
+    // low points which are walls themselves start no basin
+    let is_basin = |p: &Point| map.get(p).is_some_and(|v| *v < wall);
 
-    low_points
-        .into_iter()
-        .map(|low_point| {
-            let mut to_visit = vec![low_point];
-            let mut basin_size: usize = 0;
-
-            while let Some(p) = to_visit.pop() {
-                basin_size += 1;
-
-                let neighbors = get_neighbors(map, p);
-                neighbors.into_iter().for_each(|(neighbor, v)| {
-                    if v < 9 && !visited.contains(&neighbor) {
-                        to_visit.push(neighbor);
-                        visited.insert(neighbor);
-                    }
-                });
+    let mut labels: HashMap<Point, usize> = low_points
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| is_basin(p))
+        .map(|(id, p)| (*p, id))
+        .collect();
+
+    low_points.iter().enumerate().for_each(|(id, low_point)| {
+        if !is_basin(low_point) {
+            return;
+        }
+
+        let mut to_visit = vec![*low_point];
+
+        while let Some(p) = to_visit.pop() {
+            let neighbors = get_neighbors(map, p);
+            neighbors.into_iter().for_each(|(neighbor, v)| {
+                if v < wall && !labels.contains_key(&neighbor) {
+                    to_visit.push(neighbor);
+                    labels.insert(neighbor, id);
+                }
+            });
+        }
+    });
+
+    labels
+}
+
+/// Size of the single basin around `low_point`, independent of any other basin
+pub fn basin_size_from(map: &HightMap, low_point: Point, wall: u8) -> usize {
+    if map.get(&low_point).is_none_or(|v| *v >= wall) {
+        return 0;
+    }
+
+    let mut visited: HashSet<Point> = HashSet::from([low_point]);
+    let mut to_visit = vec![low_point];
+
+    while let Some(p) = to_visit.pop() {
+        get_neighbors(map, p).into_iter().for_each(|(neighbor, v)| {
+            if v < wall && visited.insert(neighbor) {
+                to_visit.push(neighbor);
             }
+        });
+    }
+
+    visited.len()
+}
+
+pub fn calc_basin_sizes(map: &HightMap, low_points: Vec<Point>, wall: u8) -> Vec<usize> {
+    let labels = label_basins(map, &low_points, wall);
+
+    let mut basin_sizes = vec![0; low_points.len()];
+    labels.values().for_each(|id| basin_sizes[*id] += 1);
+
+    basin_sizes
+}
+
+/// Cells of each basin next to the grid edge or outside of their basin
+pub fn basin_borders(map: &HightMap, labels: &HashMap<Point, usize>) -> HashMap<usize, Vec<Point>> {
+    let mut borders: HashMap<usize, Vec<Point>> = HashMap::new();
+
+    labels.iter().for_each(|(point, id)| {
+        let neighbors = get_neighbors(map, *point);
 
-            basin_size
+        let is_border = neighbors.len() < 4
+            || neighbors
+                .iter()
+                .any(|(neighbor, _)| labels.get(neighbor) != Some(id));
+
+        if is_border {
+            borders.entry(*id).or_default().push(*point);
+        }
+    });
+
+    borders
+}
+
+/// Map every non wall cell to the low point reached by always stepping to the lowest neighbor
+///
+/// Only strictly lower neighbors are followed, so a cell on a plateau without a lower neighbor
+/// is its own sink. There the result differs from `calc_basin_sizes`, which floods plateaus.
+pub fn watershed(map: &HightMap, wall: u8) -> HashMap<Point, Point> {
+    let mut drains_to: HashMap<Point, Point> = HashMap::new();
+
+    map.iter()
+        .filter(|(_, v)| **v < wall)
+        .for_each(|(start, _)| {
+            let mut path = vec![*start];
+            let mut current = *start;
+
+            let sink = loop {
+                if let Some(sink) = drains_to.get(&current) {
+                    break *sink;
+                }
+
+                let lowest = get_neighbors(map, current)
+                    .into_iter()
+                    .min_by_key(|(p, v)| (*v, *p))
+                    .filter(|(_, v)| *v < map[&current]);
+
+                match lowest {
+                    Some((next, _)) => {
+                        path.push(next);
+                        current = next;
+                    }
+                    None => break current,
+                }
+            };
+
+            path.into_iter().for_each(|p| {
+                drains_to.insert(p, sink);
+            });
+        });
+
+    drains_to
+}
+
+/// Number of rows and cols covered by the map
+fn dimensions(map: &HightMap) -> (usize, usize) {
+    let rows = map.keys().map(|(i, _)| i + 1).max().unwrap_or(0);
+    let cols = map.keys().map(|(_, j)| j + 1).max().unwrap_or(0);
+
+    (rows as usize, cols as usize)
+}
+
+/// Draw the map with every height three chars wide and low points in parentheses
+pub fn render_heightmap(map: &HightMap) -> String {
+    let (rows, cols) = dimensions(map);
+    let low_points: HashSet<Point> = find_low_points(map).into_iter().map(|(p, _)| p).collect();
+
+    (0..rows as isize)
+        .map(|i| {
+            (0..cols as isize)
+                .map(|j| match map.get(&(i, j)) {
+                    Some(v) if low_points.contains(&(i, j)) => format!("({})", v),
+                    Some(v) => format!(" {} ", v),
+                    None => "   ".to_owned(),
+                })
+                .collect::<String>()
         })
-        .collect()
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
-    let map = load_map(&input)?;
+/// Total risk level of the low points and product of the three largest basins
+pub fn solve(input: &str) -> Result<(usize, usize)> {
+    let map = load_map(input)?;
     let low_points_with_values = find_low_points(&map);
 
     let total_risk_level: usize = low_points_with_values
@@ -120,14 +251,17 @@ fn main() -> Result<()> {
         .map(|(point, _)| point)
         .collect();
 
-    let basin_sizes = calc_basin_sizes(&map, low_points);
+    let basin_sizes = calc_basin_sizes(&map, low_points, WALL);
 
-    let three_largest_mult: usize = basin_sizes
-        .into_iter()
-        .sorted()
-        .rev()
-        .take(3)
-        .fold(1, |acc, b| acc * b);
+    let three_largest_mult: usize = basin_sizes.into_iter().sorted().rev().take(3).product();
+
+    Ok((total_risk_level, three_largest_mult))
+}
+
+fn main() -> Result<()> {
+    let input = std::fs::read_to_string("input.txt")?;
+
+    let (total_risk_level, three_largest_mult) = solve(&input)?;
 
     println!("total_risk_level: {}", total_risk_level);
     println!("three_largest_mult: {}", three_largest_mult);
@@ -139,30 +273,148 @@ fn main() -> Result<()> {
 mod test {
     use super::*;
 
-    #[test]
-    fn load_map_working() {
-        let map_raw = r"2199943210
+    static SAMPLE_MAP: &str = r"2199943210
         3987894921
         9856789892
         8767896789
         9899965678";
 
-        let map = load_map(map_raw);
+    #[test]
+    fn load_map_working() {
+        let map = load_map(SAMPLE_MAP);
 
         assert_eq!(map.unwrap().len(), 50)
     }
 
     #[test]
     fn find_low_points_working() {
-        let map_raw = r"2199943210
-        3987894921
-        9856789892
-        8767896789
-        9899965678";
-
-        let map = load_map(map_raw).unwrap();
+        let map = load_map(SAMPLE_MAP).unwrap();
         let low_points_with_values = find_low_points(&map);
 
         assert_eq!(low_points_with_values.len(), 4)
     }
+
+    #[test]
+    fn load_map_base36_working() {
+        let map_raw = r"21a
+        398";
+
+        let map = load_map_base36(map_raw).unwrap();
+
+        assert_eq!(map.get(&(0, 2)), Some(&10));
+        assert!(load_map(map_raw).is_err());
+    }
+
+    #[test]
+    fn basin_borders_sample() {
+        let map = load_map(SAMPLE_MAP).unwrap();
+        let low_points: Vec<Point> = find_low_points(&map).into_iter().map(|(p, _)| p).collect();
+
+        let labels = label_basins(&map, &low_points, WALL);
+        let borders = basin_borders(&map, &labels);
+
+        assert_eq!(borders[&labels[&(0, 1)]].len(), 3);
+        assert_eq!(borders[&labels[&(2, 2)]].len(), 10);
+    }
+
+    #[test]
+    fn watershed_matches_basin_sizes() {
+        let map = load_map(SAMPLE_MAP).unwrap();
+        let low_points: Vec<Point> = find_low_points(&map).into_iter().map(|(p, _)| p).collect();
+
+        let mut drained: HashMap<Point, usize> = HashMap::new();
+        watershed(&map, WALL)
+            .values()
+            .for_each(|low_point| *drained.entry(*low_point).or_default() += 1);
+
+        let watershed_sizes: Vec<usize> = low_points.iter().map(|p| drained[p]).collect();
+
+        assert_eq!(drained.len(), low_points.len());
+        assert_eq!(watershed_sizes, calc_basin_sizes(&map, low_points, WALL));
+    }
+
+    #[test]
+    fn render_heightmap_highlights_low_points() {
+        let map = load_map(SAMPLE_MAP).unwrap();
+        let rendered = render_heightmap(&map);
+
+        assert_eq!(rendered.matches('(').count(), 4);
+        assert_eq!(rendered.lines().count(), 5);
+        assert!(rendered.starts_with(" 2 (1) 9 "));
+        assert!(rendered.lines().last().unwrap().contains("(5)"));
+    }
+
+    #[test]
+    fn basin_size_from_matches_batch() {
+        let map = load_map(SAMPLE_MAP).unwrap();
+        let low_points: Vec<Point> = find_low_points(&map).into_iter().map(|(p, _)| p).collect();
+
+        let independent: Vec<usize> = low_points
+            .iter()
+            .map(|p| basin_size_from(&map, *p, WALL))
+            .collect();
+
+        assert_eq!(basin_size_from(&map, (0, 1), WALL), 3);
+        assert_eq!(independent.iter().sum::<usize>(), 3 + 9 + 14 + 9);
+        assert_eq!(independent, calc_basin_sizes(&map, low_points, WALL));
+    }
+
+    #[test]
+    fn single_cell_map() {
+        let map = load_map("5").unwrap();
+
+        assert!(get_neighbors(&map, (0, 0)).is_empty());
+        assert_eq!(find_low_points(&map), vec![((0, 0), 5)]);
+        assert_eq!(calc_basin_sizes(&map, vec![(0, 0)], WALL), vec![1]);
+        assert_eq!(basin_size_from(&map, (0, 0), WALL), 1);
+    }
+
+    #[test]
+    fn single_row_map() {
+        let map = load_map("3190129").unwrap();
+        let mut low_points: Vec<Point> =
+            find_low_points(&map).into_iter().map(|(p, _)| p).collect();
+        low_points.sort();
+
+        assert_eq!(get_neighbors(&map, (0, 0)).len(), 1);
+        assert_eq!(get_neighbors(&map, (0, 3)).len(), 2);
+        assert_eq!(low_points, vec![(0, 1), (0, 3)]);
+        assert_eq!(calc_basin_sizes(&map, low_points, WALL), vec![2, 3]);
+    }
+
+    #[test]
+    fn solve_sample() {
+        assert_eq!(solve(SAMPLE_MAP).unwrap(), (15, 1134));
+    }
+
+    #[test]
+    fn single_wall_cell_map() {
+        let map = load_map("9").unwrap();
+
+        assert_eq!(find_low_points(&map), vec![((0, 0), 9)]);
+        assert_eq!(basin_size_from(&map, (0, 0), WALL), 0);
+        assert_eq!(calc_basin_sizes(&map, vec![(0, 0)], WALL), vec![0]);
+    }
+
+    #[test]
+    fn single_row_wall_low_point() {
+        let map = load_map("989").unwrap();
+
+        assert_eq!(find_low_points(&map), vec![((0, 1), 8)]);
+        assert_eq!(basin_size_from(&map, (0, 1), WALL), 1);
+        assert_eq!(basin_size_from(&map, (0, 1), 8), 0);
+        assert_eq!(calc_basin_sizes(&map, vec![(0, 1)], 8), vec![0]);
+    }
+
+    #[test]
+    fn watershed_plateau() {
+        let map = load_map("3559").unwrap();
+
+        let drains_to = watershed(&map, WALL);
+
+        assert_eq!(drains_to[&(0, 1)], (0, 0));
+        assert_eq!(drains_to[&(0, 2)], (0, 2));
+        assert!(!drains_to.contains_key(&(0, 3)));
+        assert_eq!(calc_basin_sizes(&map, vec![(0, 0)], WALL), vec![3]);
+    }
 }
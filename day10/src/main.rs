@@ -1,5 +1,6 @@
 use anyhow::{anyhow, bail, Error, Result};
 use itertools::Itertools;
+use std::collections::HashMap;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BracketMeaning {
@@ -33,6 +34,15 @@ impl Bracket {
             Bracket::Angle => 4,
         }
     }
+
+    pub fn closing(&self) -> char {
+        match self {
+            Bracket::Parentheses => ')',
+            Bracket::Square => ']',
+            Bracket::Curly => '}',
+            Bracket::Angle => '>',
+        }
+    }
 }
 
 impl TryFrom<char> for BracketMeaning {
@@ -55,12 +65,53 @@ impl TryFrom<char> for BracketMeaning {
     }
 }
 
+/// Maps chars to opening or closing brackets
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BracketConfig {
+    pub meanings: HashMap<char, BracketMeaning>,
+}
+
+impl BracketConfig {
+    pub fn empty() -> Self {
+        Self {
+            meanings: HashMap::new(),
+        }
+    }
+
+    pub fn with_pair(mut self, open: char, close: char, bracket: Bracket) -> Self {
+        self.meanings.insert(open, BracketMeaning::Opened(bracket));
+        self.meanings.insert(close, BracketMeaning::Closed(bracket));
+        self
+    }
+
+    pub fn meaning(&self, c: char) -> Result<BracketMeaning> {
+        self.meanings
+            .get(&c)
+            .copied()
+            .ok_or(anyhow!("unknown char"))
+    }
+}
+
+impl Default for BracketConfig {
+    fn default() -> Self {
+        Self::empty()
+            .with_pair('(', ')', Bracket::Parentheses)
+            .with_pair('[', ']', Bracket::Square)
+            .with_pair('{', '}', Bracket::Curly)
+            .with_pair('<', '>', Bracket::Angle)
+    }
+}
+
 fn parse_line(line: String) -> Result<(usize, Vec<Bracket>)> {
+    parse_line_with(&line, &BracketConfig::default())
+}
+
+pub fn parse_line_with(line: &str, config: &BracketConfig) -> Result<(usize, Vec<Bracket>)> {
     let mut bracket_list = Vec::<Bracket>::new();
     let mut error = 0;
 
     for c in line.chars() {
-        let token = BracketMeaning::try_from(c)?;
+        let token = config.meaning(c)?;
         let last = bracket_list.last();
 
         match (last, token) {
@@ -83,6 +134,31 @@ fn parse_line(line: String) -> Result<(usize, Vec<Bracket>)> {
     Ok((error, bracket_list))
 }
 
+/// Truncate the line at the first corrupted char and autocomplete the rest
+pub fn repair(line: &str) -> Result<String> {
+    let mut bracket_list = Vec::<Bracket>::new();
+    let mut repaired = String::new();
+
+    for c in line.chars() {
+        match BracketMeaning::try_from(c)? {
+            BracketMeaning::Opened(b) => bracket_list.push(b),
+            BracketMeaning::Closed(b) => {
+                if bracket_list.last() != Some(&b) {
+                    break;
+                }
+
+                bracket_list.pop();
+            }
+        }
+
+        repaired.push(c);
+    }
+
+    repaired.extend(bracket_list.iter().rev().map(|b| b.closing()));
+
+    Ok(repaired)
+}
+
 fn complete_line_score(line: Vec<Bracket>) -> usize {
     line.into_iter()
         .rev()
@@ -101,37 +177,196 @@ pub trait Median: Iterator {
 
         v.get(median_index).cloned()
     }
+
+    /// Same element as `median`, found by selection instead of a full sort
+    fn median_quickselect(self) -> Option<Self::Item>
+    where
+        Self: Sized,
+        Self::Item: Ord,
+    {
+        let mut v: Vec<_> = self.collect();
+        if v.is_empty() {
+            return None;
+        }
+
+        let median_index = v.len() / 2;
+        v.select_nth_unstable(median_index);
+
+        Some(v.swap_remove(median_index))
+    }
 }
 
 impl<I: Iterator> Median for I {}
 
-fn main() -> Result<()> {
-    let input: Vec<String> = std::fs::read_to_string("input.txt")?
-        .lines()
-        .map(|s| s.trim().to_owned())
-        .collect();
+fn score_line(line: &str) -> Result<(usize, Option<usize>)> {
+    let (error, open) = parse_line(line.trim().to_owned())?;
+    let completion = (error == 0).then(|| complete_line_score(open));
+
+    Ok((error, completion))
+}
+
+/// Error and (for incomplete lines) completion score of every line
+pub fn per_line_scores(lines: &[&str]) -> Result<Vec<(usize, Option<usize>)>> {
+    lines.iter().map(|line| score_line(line)).collect()
+}
 
-    let parsed_input = input
+/// Total error score and middle completion score
+fn aggregate_scores(scores: Vec<(usize, Option<usize>)>) -> (usize, Option<usize>) {
+    let total_error_score = scores.iter().map(|(e, _)| e).sum();
+
+    let middle_completion_score = scores
         .into_iter()
-        .map(parse_line)
+        .filter_map(|(_, c)| c)
+        .median_quickselect();
+
+    (total_error_score, middle_completion_score)
+}
+
+pub fn score_lines(lines: &[String]) -> Result<(usize, Option<usize>)> {
+    let scores = lines
+        .iter()
+        .map(|line| score_line(line))
         .collect::<Result<Vec<_>>>()?;
 
-    let total_error_score: usize = parsed_input.iter().map(|(e, _)| e).sum();
-    println!("Total Error Score: {}", total_error_score);
+    Ok(aggregate_scores(scores))
+}
 
-    let incomplete_input: Vec<_> = parsed_input
-        .into_iter()
-        .filter(|(e, _)| *e == 0)
-        .map(|(_, l)| l)
-        .collect();
+/// Like `score_lines` with the lines classified in parallel
+#[cfg(feature = "rayon")]
+pub fn par_score_lines(lines: &[String]) -> Result<(usize, Option<usize>)> {
+    use rayon::prelude::*;
 
-    let middle_completion_score = incomplete_input
-        .into_iter()
-        .map(complete_line_score)
-        .median()
-        .ok_or(anyhow!("Middle Completion Score not found"))?;
+    let scores = lines
+        .par_iter()
+        .map(|line| score_line(line))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(aggregate_scores(scores))
+}
+
+/// Total syntax error score and middle completion score
+pub fn solve(input: &str) -> Result<(usize, usize)> {
+    let lines: Vec<String> = input.lines().map(|l| l.trim().to_owned()).collect();
 
+    let (total_error_score, middle_completion_score) = score_lines(&lines)?;
+
+    let middle_completion_score =
+        middle_completion_score.ok_or(anyhow!("Middle Completion Score not found"))?;
+
+    Ok((total_error_score, middle_completion_score))
+}
+
+fn main() -> Result<()> {
+    let input = std::fs::read_to_string("input.txt")?;
+
+    let (total_error_score, middle_completion_score) = solve(&input)?;
+
+    println!("Total Error Score: {}", total_error_score);
     println!("Middle Completion Score: {:?}", middle_completion_score);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static SAMPLE_LINES: [&str; 10] = [
+        "[({(<(())[]>[[{[]{<()<>>",
+        "[(()[<>])]({[<{<<[]>>(",
+        "{([(<{}[<>[]}>{[]{[(<()>",
+        "(((({<>}<{<{<>}{[]{[]{}",
+        "[[<[([]))<([[{}[[()]]]",
+        "[{[{({}]{}}([{[{{{}}([]",
+        "{<[[]]>}<{[{[{[]{()[[[]",
+        "[<(<(<(<{}))><([]([]()",
+        "<{([([[(<>()){}]>(<<{{",
+        "<{([{{}}[<[[[<>{}]]]>[]]",
+    ];
+
+    #[test]
+    fn repair_corrupted_line() {
+        let line = "{([(<{}[<>[]}>{[]{[(<()>";
+
+        let repaired = repair(line).unwrap();
+        assert_eq!(repaired, "{([(<{}[<>[]]>)])}");
+
+        let (error, open) = parse_line(repaired.clone()).unwrap();
+        assert_eq!(error, 0);
+        assert!(open.is_empty());
+
+        assert_eq!(repair(&repaired).unwrap(), repaired);
+    }
+
+    #[test]
+    fn parse_line_custom_config() {
+        let config = BracketConfig::empty().with_pair('a', 'b', Bracket::Parentheses);
+
+        let (error, open) = parse_line_with("aabab", &config).unwrap();
+        assert_eq!(error, 0);
+        assert_eq!(complete_line_score(open), 1);
+
+        let (error, _) = parse_line_with("abb", &config).unwrap();
+        assert_eq!(error, 3);
+
+        assert!(parse_line_with("()", &config).is_err());
+    }
+
+    #[test]
+    fn per_line_scores_sample() {
+        let scores = per_line_scores(&SAMPLE_LINES).unwrap();
+
+        assert_eq!(
+            scores[..4],
+            [
+                (0, Some(288957)),
+                (0, Some(5566)),
+                (1197, None),
+                (0, Some(1480781))
+            ]
+        );
+        assert_eq!(scores.iter().map(|(e, _)| e).sum::<usize>(), 26397);
+    }
+
+    #[test]
+    fn median_quickselect_matches_median() {
+        let mut seed: u64 = 7;
+        let scores: Vec<usize> = (0..100_001)
+            .map(|_| {
+                seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+                (seed >> 33) as usize
+            })
+            .collect();
+
+        assert_eq!(scores.iter().median_quickselect(), scores.iter().median());
+        assert_eq!(
+            scores[..10].iter().median_quickselect(),
+            scores[..10].iter().median()
+        );
+        assert_eq!(Vec::<usize>::new().into_iter().median_quickselect(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn par_score_lines_matches_sequential() {
+        let lines: Vec<String> = SAMPLE_LINES
+            .iter()
+            .cycle()
+            .take(10_001)
+            .enumerate()
+            .map(|(i, line)| format!("{}{}", "()".repeat(i % 7), line))
+            .collect();
+
+        let sequential = score_lines(&lines).unwrap();
+
+        assert_eq!(par_score_lines(&lines).unwrap(), sequential);
+        assert_eq!(sequential.0, 26397 * 1000);
+    }
+
+    #[test]
+    fn solve_sample() {
+        let input = SAMPLE_LINES.join("\n");
+
+        assert_eq!(solve(&input).unwrap(), (26397, 288957));
+    }
+}
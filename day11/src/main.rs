@@ -1,63 +1,49 @@
-use anyhow::{anyhow, bail, Result};
-use std::collections::{HashMap, HashSet};
+use anyhow::{bail, Result};
+use grid::DigitGrid;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
-pub type Point = (isize, isize);
+pub type Point = grid::Point;
 
 pub type PosMap = HashMap<Point, usize>;
 
+pub type CompleteFunc = Box<dyn Fn(&PosMap, usize, usize, usize) -> bool>;
+
 pub fn get_neighbors(map: &PosMap, point: Point) -> Vec<Point> {
-    let (x, y) = point;
-
-    let north = (1, 0);
-    let north_east = (1, 1);
-    let east = (0, 1);
-    let south_east = (-1, 1);
-    let south = (-1, 0);
-    let south_west = (-1, -1);
-    let west = (0, -1);
-    let north_west = (1, -1);
-
-    [
-        north, north_east, east, south_east, south, south_west, west, north_west,
-    ]
-    .into_iter()
-    .filter_map(|(i, j)| {
-        let neighbor = (x + i, y + j);
-        match map.get(&neighbor) {
-            Some(_) => Some(neighbor),
-            None => None,
-        }
-    })
-    .collect()
+    let offsets: Vec<Point> = grid::ORTHOGONAL.into_iter().chain(grid::DIAGONAL).collect();
+
+    grid::neighbors(map, point, &offsets)
 }
 
 pub fn load_map(input: &str) -> Result<PosMap> {
-    input
-        .lines()
-        .enumerate()
-        .map(|(i, l)| {
-            l.trim().chars().enumerate().map(move |(j, c)| {
-                let d = c
-                    .to_digit(10)
-                    .ok_or(anyhow!("invalid char '{}' in ({}, {})", c, i, j))?
-                    as usize;
-                Ok(((i as isize, j as isize), d))
-            })
-        })
-        .flatten()
-        .collect()
+    Ok(DigitGrid::from_str(input)?.into_cells())
 }
 
-fn next_step(last_step: &PosMap) -> (PosMap, usize) {
-    let mut new_step: PosMap = last_step.iter().map(|(p, v)| (p.clone(), v + 1)).collect();
+/// Row-major flat buffer of the map together with its rows and cols
+pub fn to_grid(map: &PosMap) -> (Vec<usize>, usize, usize) {
+    let rows = map.keys().map(|(i, _)| i + 1).max().unwrap_or(0) as usize;
+    let cols = map.keys().map(|(_, j)| j + 1).max().unwrap_or(0) as usize;
+
+    let mut grid = vec![0; rows * cols];
+    map.iter()
+        .for_each(|((i, j), v)| grid[*i as usize * cols + *j as usize] = *v);
+
+    (grid, rows, cols)
+}
+
+/// Advance one step, with `reset_after_flash` false flashed cells keep their energy
+fn next_step(last_step: &PosMap, reset_after_flash: bool) -> (PosMap, usize) {
+    let mut new_step: PosMap = last_step.iter().map(|(p, v)| (*p, v + 1)).collect();
 
     let mut to_flash: Vec<Point> = new_step
         .iter()
-        .filter(|(_, v)| **v == 10)
-        .map(|(p, _)| p.clone())
+        .filter(|(_, v)| **v > 9)
+        .map(|(p, _)| *p)
         .collect();
 
-    let mut flashed: HashSet<Point> = to_flash.iter().map(|p| *p).collect();
+    let mut flashed: HashSet<Point> = to_flash.iter().copied().collect();
 
     while let Some(current) = to_flash.pop() {
         let neighbors = get_neighbors(&new_step, current);
@@ -66,25 +52,23 @@ fn next_step(last_step: &PosMap) -> (PosMap, usize) {
 
             *neighbor_value += 1;
 
-            if *neighbor_value == 10 && !flashed.contains(&neighbor_pos) {
+            if *neighbor_value > 9 && !flashed.contains(&neighbor_pos) {
                 to_flash.push(neighbor_pos);
                 flashed.insert(neighbor_pos);
             }
         });
     }
 
-    flashed
-        .iter()
-        .for_each(|p| *new_step.entry(*p).or_default() = 0);
+    if reset_after_flash {
+        flashed
+            .iter()
+            .for_each(|p| *new_step.entry(*p).or_default() = 0);
+    }
 
     (new_step, flashed.len())
 }
 
-fn run(
-    start: &PosMap,
-    steps: usize,
-    complete_func: Option<Box<dyn Fn(&PosMap, usize, usize, usize) -> bool>>,
-) -> (usize, usize, bool) {
+fn run(start: &PosMap, steps: usize, complete_func: Option<CompleteFunc>) -> (usize, usize, bool) {
     let mut map = start.clone();
 
     let mut total = 0;
@@ -93,7 +77,7 @@ fn run(
     let mut completed = complete_func.is_none();
 
     for i in 0..steps {
-        let (next_map, flashes) = next_step(&map);
+        let (next_map, flashes) = next_step(&map, true);
 
         total += flashes;
         map = next_map;
@@ -110,13 +94,50 @@ fn run(
     (total, current + 1, completed)
 }
 
-fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
-    let map = load_map(&input)?;
+/// Number of flashes within the first `steps` steps
+pub fn total_flashes(start: &PosMap, steps: usize) -> usize {
+    let (total, _, _) = run(start, steps, None);
 
-    let (total, _, _) = run(&map, 100, None);
+    total
+}
 
-    println!("Part 1 | Total Flashes: {}", total);
+/// First step (1-based) with the most flashes within `max_steps` and its flash count
+pub fn peak_flash_step(start: &PosMap, max_steps: usize) -> (usize, usize) {
+    let mut map = start.clone();
+    let mut peak = (0, 0);
+
+    for step in 1..=max_steps {
+        let (next_map, flashes) = next_step(&map, true);
+
+        if flashes > peak.1 {
+            peak = (step, flashes);
+        }
+
+        map = next_map;
+    }
+
+    peak
+}
+
+pub fn run_until_flashes(
+    start: &PosMap,
+    target_flashes: usize,
+    max_steps: usize,
+) -> Option<(usize, usize)> {
+    let (total, steps, completed) = run(
+        start,
+        max_steps,
+        Some(Box::new(move |_, total, _, _| total >= target_flashes)),
+    );
+
+    completed.then_some((steps, total))
+}
+
+/// Flashes after 100 steps and the first step where all octopuses flash
+pub fn solve(input: &str) -> Result<(usize, usize)> {
+    let map = load_map(input)?;
+
+    let total = total_flashes(&map, 100);
 
     let (_, steps_needed, completed) = run(
         &map,
@@ -130,6 +151,15 @@ fn main() -> Result<()> {
         bail!("Part 2 | Complete Condition not met.");
     }
 
+    Ok((total, steps_needed))
+}
+
+fn main() -> Result<()> {
+    let input = std::fs::read_to_string("input.txt")?;
+
+    let (total, steps_needed) = solve(&input)?;
+
+    println!("Part 1 | Total Flashes: {}", total);
     println!("Part 2 | Steps Needed: {}", steps_needed);
 
     Ok(())
@@ -176,7 +206,100 @@ mod test {
         11111";
 
         let start_map = load_map(map_raw).unwrap();
-        let first_step = next_step(&start_map);
+        let first_step = next_step(&start_map, true);
         println!("{:#?}", first_step)
     }
+
+    static SAMPLE_MAP: &str = r"5483143223
+        2745854711
+        5264556173
+        6141336146
+        6357385478
+        4167524645
+        2176841721
+        6882881134
+        4846848554
+        5283751526";
+
+    #[test]
+    fn run_until_flashes_reaches_target() {
+        let map = load_map(SAMPLE_MAP).unwrap();
+
+        let (steps, total) = run_until_flashes(&map, 1000, 100).unwrap();
+
+        assert_eq!(steps, 59);
+        assert_eq!(total, 1013);
+    }
+
+    #[test]
+    fn run_until_flashes_not_reached() {
+        let map = load_map(SAMPLE_MAP).unwrap();
+
+        assert_eq!(run_until_flashes(&map, 1000, 10), None);
+    }
+
+    #[test]
+    fn next_step_without_reset() {
+        let map = load_map(SAMPLE_MAP).unwrap();
+        let energy = |m: &PosMap| m.values().sum::<usize>();
+
+        let simulate = |reset_after_flash: bool| {
+            (0..3)
+                .scan(map.clone(), |current, _| {
+                    let (next, flashes) = next_step(current, reset_after_flash);
+                    *current = next;
+
+                    Some((energy(current), flashes))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let with_reset = simulate(true);
+        let without_reset = simulate(false);
+
+        assert!(without_reset.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(without_reset[2].0 > with_reset[2].0);
+
+        let flashes = |s: &[(usize, usize)]| s.iter().map(|(_, f)| *f).collect::<Vec<_>>();
+        assert_ne!(flashes(&with_reset), flashes(&without_reset));
+    }
+
+    #[test]
+    fn to_grid_round_trip() {
+        let map = load_map(SAMPLE_MAP).unwrap();
+
+        let (grid, rows, cols) = to_grid(&map);
+        assert_eq!((rows, cols), (10, 10));
+        assert_eq!(grid[..3], [5, 4, 8]);
+
+        let reconstructed: PosMap = grid
+            .iter()
+            .enumerate()
+            .map(|(idx, v)| (((idx / cols) as isize, (idx % cols) as isize), *v))
+            .collect();
+
+        assert_eq!(reconstructed, map);
+    }
+
+    #[test]
+    fn total_flashes_sample() {
+        let map = load_map(SAMPLE_MAP).unwrap();
+
+        assert_eq!(total_flashes(&map, 10), 204);
+        assert_eq!(total_flashes(&map, 100), 1656);
+    }
+
+    #[test]
+    fn peak_flash_step_sample() {
+        let map = load_map(SAMPLE_MAP).unwrap();
+
+        assert_eq!(peak_flash_step(&map, 200), (195, 100));
+        assert_eq!(peak_flash_step(&map, 10), (3, 45));
+        assert_eq!(peak_flash_step(&map, 0), (0, 0));
+    }
+
+    #[test]
+    fn solve_sample() {
+        assert_eq!(solve(SAMPLE_MAP).unwrap(), (1656, 195));
+    }
 }
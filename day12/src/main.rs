@@ -1,8 +1,11 @@
 use anyhow::{anyhow, bail, Error, Result};
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
 #[derive(Clone, Hash, PartialEq, Eq)]
-enum Cave {
+pub enum Cave {
     Start,
     End,
     Big(String),
@@ -20,30 +23,54 @@ impl std::fmt::Debug for Cave {
     }
 }
 
-impl FromStr for Cave {
-    type Err = Error;
+/// Decides whether a cave is big or small, explicit overrides win over the name's case
+#[derive(Debug, Clone, Default)]
+pub struct CaveClassifier {
+    overrides: HashMap<String, bool>,
+}
 
-    fn from_str(s: &str) -> Result<Self> {
-        let cave = match s.trim() {
+impl CaveClassifier {
+    /// Treat `name` (matched case-insensitively) as a big cave
+    pub fn big(mut self, name: &str) -> Self {
+        self.overrides.insert(name.to_lowercase(), true);
+        self
+    }
+
+    /// Treat `name` (matched case-insensitively) as a small cave
+    pub fn small(mut self, name: &str) -> Self {
+        self.overrides.insert(name.to_lowercase(), false);
+        self
+    }
+
+    pub fn classify(&self, s: &str) -> Result<Cave> {
+        let s = s.trim();
+
+        let cave = match s {
             "start" => Cave::Start,
             "end" => Cave::End,
-            _ => {
-                if s.chars().all(|c| c.is_uppercase()) {
-                    Cave::Big(s.to_owned())
-                } else if s.chars().all(|c| c.is_lowercase()) {
-                    Cave::Small(s.to_owned())
-                } else {
-                    bail!("Neither small nor big cave: {}", s)
-                }
-            }
+            _ => match self.overrides.get(&s.to_lowercase()) {
+                Some(true) => Cave::Big(s.to_owned()),
+                Some(false) => Cave::Small(s.to_owned()),
+                None if s.chars().all(|c| c.is_uppercase()) => Cave::Big(s.to_owned()),
+                None if s.chars().all(|c| c.is_lowercase()) => Cave::Small(s.to_owned()),
+                None => bail!("Neither small nor big cave: {}", s),
+            },
         };
 
         Ok(cave)
     }
 }
 
+impl FromStr for Cave {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        CaveClassifier::default().classify(s)
+    }
+}
+
 #[derive(Debug, Clone)]
-struct CaveNetwork {
+pub struct CaveNetwork {
     network: HashMap<Cave, Vec<Cave>>,
 }
 
@@ -51,15 +78,26 @@ impl FromStr for CaveNetwork {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
+        CaveNetwork::parse_with(s, &CaveClassifier::default())
+    }
+}
+
+impl CaveNetwork {
+    pub fn parse_with(s: &str, classifier: &CaveClassifier) -> Result<Self> {
         let mut network: HashMap<Cave, Vec<Cave>> = HashMap::new();
 
         (s.trim().lines().try_for_each(|line| {
             let split: Vec<&str> = line.split("-").map(|s| s.trim()).collect();
-            let left = split.get(0).ok_or(anyhow!("Left cave not found"))?;
+            let left = split.first().ok_or(anyhow!("Left cave not found"))?;
             let right = split.get(1).ok_or(anyhow!("Right cave not found"))?;
 
-            let left_cave = Cave::from_str(&left)?;
-            let right_cave = Cave::from_str(&right)?;
+            let left_cave = classifier.classify(left)?;
+            let right_cave = classifier.classify(right)?;
+
+            // two connected big caves could be walked back and forth forever
+            if matches!((&left_cave, &right_cave), (Cave::Big(_), Cave::Big(_))) {
+                bail!("big caves {} and {} must not be connected", left, right);
+            }
 
             network
                 .entry(left_cave.clone())
@@ -74,10 +112,141 @@ impl FromStr for CaveNetwork {
     }
 }
 
-struct CavePathFinder {
-    pub visited: Vec<Cave>,
-    pub connections: Vec<Cave>,
-    pub small_caves_visited: HashMap<Cave, usize>,
+/// Cave network with caves interned to ids and small caves mapped to bits
+struct InternedNetwork {
+    caves: Vec<Cave>,
+    neighbors: Vec<Vec<usize>>,
+    small_bits: Vec<u64>,
+    start: usize,
+    end: usize,
+}
+
+impl InternedNetwork {
+    fn new(network: &CaveNetwork) -> Result<Self> {
+        let caves: Vec<Cave> = network.network.keys().cloned().collect();
+
+        let ids: HashMap<&Cave, usize> = caves.iter().enumerate().map(|(i, c)| (c, i)).collect();
+
+        let neighbors = caves
+            .iter()
+            .map(|cave| network.network[cave].iter().map(|c| ids[c]).collect())
+            .collect();
+
+        // only small caves take a bit of the visited mask, big caves are never tracked
+        let small_count = caves.iter().filter(|c| matches!(c, Cave::Small(_))).count();
+
+        if small_count > u64::BITS as usize {
+            bail!("too many small caves to intern: {}", small_count);
+        }
+
+        let mut next_bit = 0;
+        let small_bits = caves
+            .iter()
+            .map(|cave| match cave {
+                Cave::Small(_) => {
+                    next_bit += 1;
+                    1 << (next_bit - 1)
+                }
+                _ => 0,
+            })
+            .collect();
+
+        let start = *ids.get(&Cave::Start).ok_or(anyhow!("start not found"))?;
+        let end = *ids.get(&Cave::End).ok_or(anyhow!("end not found"))?;
+
+        Ok(Self {
+            caves,
+            neighbors,
+            small_bits,
+            start,
+            end,
+        })
+    }
+
+    /// Number of paths from `cave` to end, memoized on the visited small caves
+    fn count_from(
+        &self,
+        cave: usize,
+        small_caves_visited: u64,
+        visited_twice: bool,
+        single_small_cave_can_be_visited_twice: bool,
+        memo: &mut HashMap<(usize, u64, bool), usize>,
+    ) -> usize {
+        if cave == self.end {
+            return 1;
+        }
+
+        let key = (cave, small_caves_visited, visited_twice);
+        if let Some(count) = memo.get(&key) {
+            return *count;
+        }
+
+        let count = self.neighbors[cave]
+            .iter()
+            .filter(|next| **next != self.start)
+            .map(|&next| {
+                let bit = self.small_bits[next];
+
+                if small_caves_visited & bit == 0 {
+                    self.count_from(
+                        next,
+                        small_caves_visited | bit,
+                        visited_twice,
+                        single_small_cave_can_be_visited_twice,
+                        memo,
+                    )
+                } else if single_small_cave_can_be_visited_twice && !visited_twice {
+                    self.count_from(
+                        next,
+                        small_caves_visited,
+                        true,
+                        single_small_cave_can_be_visited_twice,
+                        memo,
+                    )
+                } else {
+                    0
+                }
+            })
+            .sum();
+
+        memo.insert(key, count);
+
+        count
+    }
+
+    /// Iterative DFS calling `on_end` with the cave ids of every path from start to end
+    fn walk(&self, single_small_cave_can_be_visited_twice: bool, mut on_end: impl FnMut(&[usize])) {
+        let mut path: Vec<usize> = Vec::new();
+        let mut stack: Vec<(usize, u64, bool, usize)> = vec![(self.start, 0, false, 0)];
+
+        while let Some((cave, small_caves_visited, visited_twice, depth)) = stack.pop() {
+            path.truncate(depth);
+            path.push(cave);
+
+            if cave == self.end {
+                on_end(&path);
+                continue;
+            }
+
+            for &next in self.neighbors[cave].iter() {
+                if next == self.start {
+                    continue;
+                }
+
+                let bit = self.small_bits[next];
+
+                let (small_caves_visited, visited_twice) = if small_caves_visited & bit == 0 {
+                    (small_caves_visited | bit, visited_twice)
+                } else if single_small_cave_can_be_visited_twice && !visited_twice {
+                    (small_caves_visited, true)
+                } else {
+                    continue;
+                };
+
+                stack.push((next, small_caves_visited, visited_twice, depth + 1));
+            }
+        }
+    }
 }
 
 impl CaveNetwork {
@@ -85,83 +254,86 @@ impl CaveNetwork {
         &self,
         single_small_cave_can_be_visited_twice: bool,
     ) -> Result<Vec<Vec<Cave>>> {
+        let interned = InternedNetwork::new(self)?;
         let mut paths = Vec::<Vec<Cave>>::new();
 
-        let start_connections = self
-            .network
-            .get(&Cave::Start)
-            .ok_or(anyhow!("start not found"))?;
-
-        let mut path_finder: Vec<CavePathFinder> = vec![CavePathFinder {
-            visited: vec![Cave::Start],
-            connections: start_connections.clone(),
-            small_caves_visited: HashMap::new(),
-        }];
-
-        while let Some(pf) = path_finder.pop() {
-            pf.connections.iter().for_each(|cave| {
-                let mut small_caves_visited = pf.small_caves_visited.clone();
-                if matches!(cave, Cave::Small(_)) {
-                    *small_caves_visited.entry(cave.clone()).or_default() += 1;
-                }
+        interned.walk(single_small_cave_can_be_visited_twice, |path| {
+            paths.push(path.iter().map(|id| interned.caves[*id].clone()).collect())
+        });
 
-                let visited: Vec<Cave> = pf
-                    .visited
-                    .clone()
-                    .into_iter()
-                    .chain(vec![cave.clone()].into_iter())
-                    .collect();
-
-                let no_small_cave_visited_twice =
-                    small_caves_visited.values().filter(|f| **f > 1).count() == 0;
-
-                let visited_filter: Box<dyn Fn(&Cave) -> bool> =
-                    match single_small_cave_can_be_visited_twice {
-                        true => Box::new(|to_check| {
-                            !matches!(to_check, Cave::Small(_))
-                                || !small_caves_visited.contains_key(to_check)
-                                || no_small_cave_visited_twice
-                        }),
-                        false => Box::new(|to_check| !small_caves_visited.contains_key(to_check)),
-                    };
-
-                let connections = self
-                    .network
-                    .get(cave)
-                    .expect("cave not found")
-                    .clone()
-                    .into_iter()
-                    .filter(|f| !matches!(f, Cave::Start))
-                    .filter(visited_filter)
-                    .collect();
-
-                match cave {
-                    Cave::End => paths.push(visited),
-                    _ => {
-                        path_finder.push(CavePathFinder {
-                            connections,
-                            small_caves_visited,
-                            visited,
-                        });
-                    }
-                }
-            });
+        Ok(paths)
+    }
+
+    /// Caves (except start and end) connected to only one other cave
+    pub fn dead_ends(&self) -> Vec<Cave> {
+        self.network
+            .iter()
+            .filter(|(cave, _)| !matches!(cave, Cave::Start | Cave::End))
+            .filter(|(_, connections)| connections.iter().collect::<HashSet<_>>().len() == 1)
+            .map(|(cave, _)| cave.clone())
+            .collect()
+    }
+
+    pub fn cave_count(&self) -> usize {
+        self.network.len()
+    }
+
+    /// Whether every cave can be reached from start
+    pub fn is_connected(&self) -> bool {
+        let mut reached: HashSet<&Cave> = HashSet::new();
+        let mut to_visit = vec![&Cave::Start];
+
+        while let Some(cave) = to_visit.pop() {
+            if !reached.insert(cave) {
+                continue;
+            }
+
+            if let Some(connections) = self.network.get(cave) {
+                to_visit.extend(connections.iter());
+            }
         }
 
-        Ok(paths)
+        reached.len() == self.cave_count()
     }
+
+    /// Same as `count_paths` but counts shared suffixes only once
+    pub fn count_paths_memo(&self, single_small_cave_can_be_visited_twice: bool) -> Result<usize> {
+        let interned = InternedNetwork::new(self)?;
+        let mut memo = HashMap::new();
+
+        Ok(interned.count_from(
+            interned.start,
+            0,
+            false,
+            single_small_cave_can_be_visited_twice,
+            &mut memo,
+        ))
+    }
+
+    pub fn count_paths(&self, single_small_cave_can_be_visited_twice: bool) -> Result<usize> {
+        let interned = InternedNetwork::new(self)?;
+        let mut count = 0;
+
+        interned.walk(single_small_cave_can_be_visited_twice, |_| count += 1);
+
+        Ok(count)
+    }
+}
+
+/// Number of paths visiting small caves at most once, and with one small cave twice
+pub fn solve(input: &str) -> Result<(usize, usize)> {
+    let network = CaveNetwork::from_str(input)?;
+
+    Ok((network.count_paths(false)?, network.count_paths(true)?))
 }
 
 fn main() -> Result<()> {
     let input = std::fs::read_to_string("input.txt")?;
 
-    let network = CaveNetwork::from_str(&input)?;
+    let (num_paths_1, num_paths_2) = solve(&input)?;
 
-    let all_paths_1 = network.all_paths(false)?;
-    println!("Part1 | num paths: {}", all_paths_1.len());
-
-    let all_paths_2 = network.all_paths(true)?;
-    println!("Part2 | num paths: {}", all_paths_2.len());
+    println!("Part1 | num paths: {}", num_paths_1);
+    println!("Part2 | num paths: {}", num_paths_2);
 
     Ok(())
 }
@@ -234,4 +406,167 @@ mod test {
 
         assert_eq!(paths.len(), 103)
     }
+
+    static TEST_NETWORK_LARGE: &str = r"fs-end
+    he-DX
+    fs-he
+    start-DX
+    pj-DX
+    end-zg
+    zg-sl
+    zg-pj
+    pj-he
+    RW-he
+    fs-DX
+    pj-RW
+    zg-RW
+    start-pj
+    he-WI
+    zg-he
+    pj-fs
+    start-RW";
+
+    #[test]
+    fn count_paths_samples() {
+        let expected = [
+            (TEST_NETWORK_SMALL, 10, 36),
+            (TEST_NETWORK_MEDIUM, 19, 103),
+            (TEST_NETWORK_LARGE, 226, 3509),
+        ];
+
+        for (raw, p1, p2) in expected {
+            let network = CaveNetwork::from_str(raw).unwrap();
+
+            assert_eq!(network.count_paths(false).unwrap(), p1);
+            assert_eq!(network.count_paths(true).unwrap(), p2);
+            assert_eq!(network.all_paths(false).unwrap().len(), p1);
+            assert_eq!(network.all_paths(true).unwrap().len(), p2);
+        }
+    }
+
+    #[test]
+    fn all_paths_start_to_end() {
+        let network = CaveNetwork::from_str(TEST_NETWORK_SMALL).unwrap();
+
+        network.all_paths(true).unwrap().iter().for_each(|path| {
+            assert_eq!(path.first(), Some(&Cave::Start));
+            assert_eq!(path.last(), Some(&Cave::End));
+        });
+    }
+
+    #[test]
+    fn dead_ends_medium() {
+        let network = CaveNetwork::from_str(TEST_NETWORK_MEDIUM).unwrap();
+
+        let dead_ends: HashSet<Cave> = network.dead_ends().into_iter().collect();
+        let expected: HashSet<Cave> = [Cave::Small("sa".to_owned()), Cave::Big("LN".to_owned())]
+            .into_iter()
+            .collect();
+
+        assert_eq!(dead_ends, expected);
+    }
+
+    #[test]
+    fn count_paths_large_graph() {
+        let extra = [
+            "zg-XY", "XY-ab", "ab-he", "XY-cd", "cd-fs", "cd-pj", "ab-end",
+        ];
+        let raw = format!("{}\n{}", TEST_NETWORK_LARGE, extra.join("\n"));
+        let network = CaveNetwork::from_str(&raw).unwrap();
+
+        let count = network.count_paths(true).unwrap();
+
+        assert_eq!(network.count_paths_memo(true).unwrap(), count);
+        assert_eq!(count, network.all_paths(true).unwrap().len());
+    }
+
+    #[test]
+    fn connectivity() {
+        let network = CaveNetwork::from_str(TEST_NETWORK_SMALL).unwrap();
+        assert_eq!(network.cave_count(), 6);
+        assert!(network.is_connected());
+
+        let disconnected = CaveNetwork::from_str(
+            r"start-A
+            A-b
+            c-end",
+        )
+        .unwrap();
+
+        assert_eq!(disconnected.cave_count(), 5);
+        assert!(!disconnected.is_connected());
+        assert_eq!(disconnected.count_paths(false).unwrap(), 0);
+    }
+
+    #[test]
+    fn count_paths_memo_matches() {
+        for raw in [TEST_NETWORK_SMALL, TEST_NETWORK_MEDIUM, TEST_NETWORK_LARGE] {
+            let network = CaveNetwork::from_str(raw).unwrap();
+
+            for twice in [false, true] {
+                assert_eq!(
+                    network.count_paths_memo(twice).unwrap(),
+                    network.count_paths(twice).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn classifier_overrides() {
+        let raw = r"start-Ab
+        Ab-c
+        Ab-end";
+
+        assert!(CaveNetwork::from_str(raw).is_err());
+
+        let small = CaveClassifier::default().small("ab");
+        assert_eq!(small.classify("Ab").unwrap(), Cave::Small("Ab".to_owned()));
+        assert_eq!(small.classify("XY").unwrap(), Cave::Big("XY".to_owned()));
+
+        let network = CaveNetwork::parse_with(raw, &small).unwrap();
+        assert_eq!(network.count_paths(false).unwrap(), 1);
+
+        let big = CaveClassifier::default().big("Ab");
+        let network = CaveNetwork::parse_with(raw, &big).unwrap();
+        assert_eq!(network.count_paths(false).unwrap(), 2);
+    }
+
+    #[test]
+    fn connected_big_caves_rejected() {
+        assert!(CaveNetwork::from_str("start-AB\nAB-CD\nCD-end").is_err());
+
+        let raw = r"start-ab
+        ab-CD
+        CD-end";
+        assert!(CaveNetwork::from_str(raw).is_ok());
+
+        let big = CaveClassifier::default().big("ab");
+        let err = CaveNetwork::parse_with(raw, &big).unwrap_err().to_string();
+        assert!(err.contains("ab and CD"), "{}", err);
+    }
+
+    #[test]
+    fn solve_samples() {
+        assert_eq!(solve(TEST_NETWORK_SMALL).unwrap(), (10, 36));
+        assert_eq!(solve(TEST_NETWORK_MEDIUM).unwrap(), (19, 103));
+        assert_eq!(solve(TEST_NETWORK_LARGE).unwrap(), (226, 3509));
+    }
+
+    #[test]
+    fn many_big_caves() {
+        let big: Vec<String> = (0..70)
+            .map(|i| format!("{}{}", (b'A' + i / 26) as char, (b'A' + i % 26) as char))
+            .collect();
+
+        let raw: Vec<String> = std::iter::once("start-b".to_owned())
+            .chain(big.iter().map(|c| format!("b-{}\n{}-end", c, c)))
+            .collect();
+        let network = CaveNetwork::from_str(&raw.join("\n")).unwrap();
+
+        assert_eq!(network.cave_count(), 73);
+        assert_eq!(network.count_paths(false).unwrap(), 70);
+        assert_eq!(network.count_paths(true).unwrap(), 70 + 70 * 70);
+        assert_eq!(network.count_paths_memo(true).unwrap(), 70 + 70 * 70);
+    }
 }
@@ -5,7 +5,7 @@ pub type Point = (usize, usize);
 pub type Paper = HashSet<Point>;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-enum Instruction {
+pub enum Instruction {
     Left(usize),
     Up(usize),
 }
@@ -68,25 +68,83 @@ fn get_paper_dimension(paper: &Paper) -> Point {
     (y_max, x_max)
 }
 
+fn fold_single(point: Point, instruction: Instruction) -> Point {
+    let (y, x) = point;
+
+    match instruction {
+        Instruction::Left(v) => {
+            if x < v {
+                (y, x)
+            } else {
+                (y, v * 2 - x)
+            }
+        }
+        Instruction::Up(v) => {
+            if y < v {
+                (y, x)
+            } else {
+                (v * 2 - y, x)
+            }
+        }
+    }
+}
+
+fn on_fold_line(point: &Point, instruction: Instruction) -> bool {
+    match instruction {
+        Instruction::Left(v) => point.1 == v,
+        Instruction::Up(v) => point.0 == v,
+    }
+}
+
+/// Number of dots lying exactly on the fold line, these vanish when folding
+pub fn dots_on_fold_line(paper: &Paper, instruction: Instruction) -> usize {
+    paper
+        .iter()
+        .filter(|point| on_fold_line(point, instruction))
+        .count()
+}
+
 fn fold_paper(paper: &Paper, instruction: Instruction) -> Paper {
     paper
         .iter()
-        .map(|(y, x)| match (y, x, instruction) {
-            (y, x, Instruction::Left(v)) => {
-                if *x < v {
-                    (*y, *x)
-                } else {
-                    (*y, v * 2 - *x)
-                }
-            }
-            (y, x, Instruction::Up(v)) => {
-                if *y < v {
-                    (*y, *x)
-                } else {
-                    (v * 2 - *y, *x)
+        .filter(|point| !on_fold_line(point, instruction))
+        .map(|point| fold_single(*point, instruction))
+        .collect()
+}
+
+/// Position of a single dot after applying all instructions, `None` once it lies on a fold line
+pub fn fold_point(point: Point, instructions: &[Instruction]) -> Option<Point> {
+    instructions.iter().try_fold(point, |p, i| {
+        if on_fold_line(&p, *i) {
+            return None;
+        }
+
+        Some(fold_single(p, *i))
+    })
+}
+
+/// Drop folds which cannot move any dot, i.e. folds at or beyond an earlier fold on the same axis
+pub fn optimize_instructions(instrs: &[Instruction]) -> Vec<Instruction> {
+    let mut x_bound: Option<usize> = None;
+    let mut y_bound: Option<usize> = None;
+
+    instrs
+        .iter()
+        .filter(|instruction| {
+            let (bound, v) = match instruction {
+                Instruction::Left(v) => (&mut x_bound, *v),
+                Instruction::Up(v) => (&mut y_bound, *v),
+            };
+
+            match bound {
+                Some(b) if v >= *b => false,
+                _ => {
+                    *bound = Some(v);
+                    true
                 }
             }
         })
+        .copied()
         .collect()
 }
 
@@ -94,10 +152,10 @@ fn split_input(input: &str) -> Result<(Paper, Vec<Instruction>)> {
     let (paper_raw, instructions_raw) =
         input.split_once("\n\n").ok_or(anyhow!("input malformed"))?;
 
-    let paper = load_paper(&paper_raw)?;
+    let paper = load_paper(paper_raw)?;
     let instructions = instructions_raw
         .lines()
-        .map(|l| Instruction::from_str(l))
+        .map(Instruction::from_str)
         .collect::<Result<_>>()?;
 
     Ok((paper, instructions))
@@ -113,7 +171,7 @@ fn print_paper(paper: &Paper) {
                 None => print!(" "),
             }
         }
-        print!("\n");
+        println!();
     }
 }
 
@@ -188,6 +246,70 @@ mod test {
     fn fold_paper_correctly() {
         let paper_org = load_paper(SAMPLE_PAPER).unwrap();
         let paper_folded_once = fold_paper(&paper_org, Instruction::Up(7));
-        assert_eq!(paper_folded_once.len(), 7);
+        assert_eq!(paper_folded_once.len(), 17);
+    }
+
+    #[test]
+    fn fold_point_matches_folded_paper() {
+        let paper_org = load_paper(SAMPLE_PAPER).unwrap();
+        let instructions = [Instruction::Up(7), Instruction::Left(5)];
+
+        let final_paper = instructions.iter().fold(paper_org, |paper, instruction| {
+            fold_paper(&paper, *instruction)
+        });
+
+        let folded = fold_point((10, 6), &instructions);
+
+        assert_eq!(folded, Some((4, 4)));
+        assert!(final_paper.contains(&folded.unwrap()));
+    }
+
+    #[test]
+    fn fold_point_on_fold_line() {
+        let instructions = [Instruction::Up(7), Instruction::Left(5)];
+
+        assert_eq!(fold_point((7, 5), &instructions), None);
+        // lands on the second fold line after the first fold
+        assert_eq!(fold_point((10, 5), &instructions), None);
+        assert_eq!(fold_point((10, 6), &instructions[..1]), Some((4, 6)));
+    }
+
+    #[test]
+    fn dots_on_fold_line_removed() {
+        let mut paper = load_paper(SAMPLE_PAPER).unwrap();
+        assert_eq!(dots_on_fold_line(&paper, Instruction::Up(7)), 0);
+
+        paper.insert((7, 5));
+        assert_eq!(dots_on_fold_line(&paper, Instruction::Up(7)), 1);
+        assert_eq!(dots_on_fold_line(&paper, Instruction::Left(5)), 1);
+
+        let folded = fold_paper(&paper, Instruction::Up(7));
+        assert!(!folded.contains(&(7, 5)));
+        assert_eq!(folded.len(), 17);
+    }
+
+    #[test]
+    fn optimize_instructions_same_paper() {
+        let paper_org = load_paper(SAMPLE_PAPER).unwrap();
+        let instructions = [
+            Instruction::Up(7),
+            Instruction::Up(9),
+            Instruction::Left(5),
+            Instruction::Up(7),
+            Instruction::Left(6),
+        ];
+
+        let optimized = optimize_instructions(&instructions);
+        assert_eq!(optimized, vec![Instruction::Up(7), Instruction::Left(5)]);
+
+        let fold_all = |instructions: &[Instruction]| {
+            instructions
+                .iter()
+                .fold(paper_org.clone(), |paper, instruction| {
+                    fold_paper(&paper, *instruction)
+                })
+        };
+
+        assert_eq!(fold_all(&optimized), fold_all(&instructions));
     }
 }
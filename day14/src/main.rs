@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use anyhow::{anyhow, bail, Result};
 
-type RuleBook = HashMap<([char; 2]), char>;
+type RuleBook = HashMap<[char; 2], char>;
 type Template = Vec<char>;
+type ElementCounts = Vec<(char, usize)>;
 
 fn get_rule_book(raw: &str) -> Result<RuleBook> {
     raw.lines()
@@ -13,7 +14,7 @@ fn get_rule_book(raw: &str) -> Result<RuleBook> {
                 .split_once(" -> ")
                 .ok_or(anyhow!("invalid rule: {}", l))?;
 
-            let from: Result<([char; 2]), _> =
+            let from: Result<[char; 2], _> =
                 TryInto::try_into(from_raw.trim().chars().collect::<Vec<_>>());
 
             let to = to_raw.trim().chars().take(1).last();
@@ -26,6 +27,19 @@ fn get_rule_book(raw: &str) -> Result<RuleBook> {
         .collect()
 }
 
+/// Map every inserted element to the (sorted) pairs inserting it
+pub fn reverse_rules(rules: &RuleBook) -> HashMap<char, Vec<[char; 2]>> {
+    let mut reversed: HashMap<char, Vec<[char; 2]>> = HashMap::new();
+
+    rules
+        .iter()
+        .for_each(|(pair, inserted)| reversed.entry(*inserted).or_default().push(*pair));
+
+    reversed.values_mut().for_each(|pairs| pairs.sort());
+
+    reversed
+}
+
 fn load_input(input: &str) -> Result<(Template, RuleBook)> {
     let (template_raw, rules_raw) = input
         .trim()
@@ -33,7 +47,24 @@ fn load_input(input: &str) -> Result<(Template, RuleBook)> {
         .ok_or(anyhow!("invalid input"))?;
 
     let template = template_raw.trim().chars().collect();
-    let rule_book = get_rule_book(&rules_raw)?;
+    let rule_book = get_rule_book(rules_raw)?;
+
+    Ok((template, rule_book))
+}
+
+/// Like `load_input` but errors if a pair formable from the present elements has no rule
+fn load_input_strict(input: &str) -> Result<(Template, RuleBook)> {
+    let (template, rule_book) = load_input(input)?;
+
+    let alphabet: BTreeSet<char> = template.iter().chain(rule_book.values()).copied().collect();
+
+    for a in alphabet.iter() {
+        for b in alphabet.iter() {
+            if !rule_book.contains_key(&[*a, *b]) {
+                bail!("rule book incomplete, no rule for pair: {}{}", a, b);
+            }
+        }
+    }
 
     Ok((template, rule_book))
 }
@@ -65,17 +96,30 @@ fn max_min_diff(template: &Template) -> Result<usize> {
         .iter()
         .for_each(|c| *map.entry(*c).or_default() += 1);
 
-    let max = map.values().max().ok_or(anyhow!("max not found"))?;
-    let min = map.values().min().ok_or(anyhow!("max not found"))?;
+    let (ascending, descending) = extremes(&map);
+
+    let (_, max) = descending.first().ok_or(anyhow!("max not found"))?;
+    let (_, min) = ascending.first().ok_or(anyhow!("min not found"))?;
 
     println!("{} {}", max, min);
 
     Ok(*max - *min)
 }
 
+/// Elements with their counts sorted ascending and descending, ties ordered by element
+pub fn extremes(counts: &HashMap<char, usize>) -> (ElementCounts, ElementCounts) {
+    let mut ascending: ElementCounts = counts.iter().map(|(c, n)| (*c, *n)).collect();
+    ascending.sort_by_key(|(c, n)| (*n, *c));
+
+    let mut descending = ascending.clone();
+    descending.sort_by_key(|(c, n)| (std::cmp::Reverse(*n), *c));
+
+    (ascending, descending)
+}
+
 fn run(template: Template, rule_book: &RuleBook, steps: usize) -> Result<Template> {
-    (0..steps).into_iter().try_fold(template, |current, _| {
-        apply_template_p1(&current, &rule_book)
+    (0..steps).try_fold(template, |current, _| {
+        apply_template_p1(&current, rule_book)
     })
 }
 
@@ -113,17 +157,24 @@ fn run_p2(template: Template, rule_book: &RuleBook, steps: usize) -> usize {
     max - min
 }
 
+/// Difference of the most and least common element after `steps` insertion steps
+pub fn solve_steps(input: &str, steps: usize) -> Result<usize> {
+    let (template, rule_book) = load_input_strict(input)?;
+
+    Ok(run_p2(template, &rule_book, steps))
+}
+
 fn main() -> Result<()> {
     let input = std::fs::read_to_string("input.txt")?;
 
-    let (template_start, rule_book) = load_input(&input)?;
+    let (template_start, rule_book) = load_input_strict(&input)?;
 
-    let template_after_10_steps = run(template_start.clone(), &rule_book, 10)?;
+    let template_after_10_steps = run(template_start, &rule_book, 10)?;
     let diff_p1 = max_min_diff(&template_after_10_steps)?;
 
     println!("Part 1: {}", diff_p1);
 
-    let diff_p2 = run_p2(template_start.clone(), &rule_book, 40);
+    let diff_p2 = solve_steps(&input, 40)?;
 
     println!("Part 2: {}", diff_p2);
 
@@ -174,4 +225,73 @@ mod test {
         let new_template = run_p2(template, &rule_book, 2);
         assert_eq!(new_template, 5);
     }
+
+    #[test]
+    fn load_input_strict_missing_rule() {
+        let rules: String = EXAMPLE_RULES
+            .lines()
+            .filter(|l| l.trim() != "CH -> B")
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let complete = format!("NNCB\n\n{}", EXAMPLE_RULES);
+        assert!(load_input_strict(&complete).is_ok());
+
+        let incomplete = format!("NNCB\n\n{}", rules);
+        assert_eq!(
+            load_input_strict(&incomplete).unwrap_err().to_string(),
+            "rule book incomplete, no rule for pair: CH"
+        );
+    }
+
+    #[test]
+    fn reverse_rules_sample() {
+        let rule_book = get_rule_book(EXAMPLE_RULES).unwrap();
+
+        let reversed = reverse_rules(&rule_book);
+
+        assert_eq!(
+            reversed[&'B'],
+            vec![
+                ['B', 'C'],
+                ['B', 'N'],
+                ['C', 'H'],
+                ['H', 'C'],
+                ['N', 'B'],
+                ['N', 'C']
+            ]
+        );
+        assert_eq!(reversed.values().map(|p| p.len()).sum::<usize>(), 16);
+    }
+
+    #[test]
+    fn solve_steps_sample() {
+        let input = format!("NNCB\n\n{}", EXAMPLE_RULES);
+
+        assert_eq!(solve_steps(&input, 10).unwrap(), 1588);
+        assert_eq!(solve_steps(&input, 40).unwrap(), 2188189693529);
+    }
+
+    #[test]
+    fn extremes_after_10_steps() {
+        let rule_book = get_rule_book(EXAMPLE_RULES).unwrap();
+        let template = run(vec!['N', 'N', 'C', 'B'], &rule_book, 10).unwrap();
+
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        template
+            .iter()
+            .for_each(|c| *counts.entry(*c).or_default() += 1);
+
+        let (ascending, descending) = extremes(&counts);
+
+        assert_eq!(
+            ascending,
+            vec![('H', 161), ('C', 298), ('N', 865), ('B', 1749)]
+        );
+        assert_eq!(
+            descending,
+            vec![('B', 1749), ('N', 865), ('C', 298), ('H', 161)]
+        );
+        assert_eq!(max_min_diff(&template).unwrap(), 1588);
+    }
 }
@@ -1,7 +1,7 @@
-use pathfinding::directed::dijkstra::dijkstra;
-use std::collections::HashMap;
+use pathfinding::directed::{astar::astar, dijkstra::dijkstra};
+use std::collections::{HashMap, HashSet};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 
 type Point = (isize, isize);
 type Cavern = HashMap<Point, usize>;
@@ -10,7 +10,7 @@ pub fn load_map(input: &str) -> Result<Cavern> {
     input
         .lines()
         .enumerate()
-        .map(|(i, l)| {
+        .flat_map(|(i, l)| {
             l.trim().chars().enumerate().map(move |(j, c)| {
                 let d = c
                     .to_digit(10)
@@ -19,10 +19,34 @@ pub fn load_map(input: &str) -> Result<Cavern> {
                 Ok(((i as isize, j as isize), d))
             })
         })
-        .flatten()
         .collect()
 }
 
+pub fn from_grid(rows: Vec<Vec<usize>>) -> Cavern {
+    rows.into_iter()
+        .enumerate()
+        .flat_map(|(i, row)| {
+            row.into_iter()
+                .enumerate()
+                .map(move |(j, risk)| ((i as isize, j as isize), risk))
+        })
+        .collect()
+}
+
+/// Dimensions (rows, cols) of a cavern without its input string
+pub fn cavern_dim(cavern: &Cavern) -> (usize, usize) {
+    let y = cavern.keys().map(|(i, _)| i + 1).max().unwrap_or(0);
+    let x = cavern.keys().map(|(_, j)| j + 1).max().unwrap_or(0);
+
+    (y as usize, x as usize)
+}
+
+pub fn cavern_destination(cavern: &Cavern) -> Point {
+    let (y, x) = cavern_dim(cavern);
+
+    (y as isize - 1, x as isize - 1)
+}
+
 fn get_dim(input: &str) -> (usize, usize) {
     let y = input.lines().count();
     let x = input.lines().take(1).collect::<Vec<_>>()[0].len();
@@ -58,13 +82,11 @@ pub fn get_neighbors(map: &Cavern, point: Point) -> Vec<(Point, usize)> {
         (right, right_value.cloned()),
     ]
     .iter()
-    .filter_map(|(point, o)| match o {
-        Some(v) => Some((*point, *v)),
-        None => None,
-    })
+    .filter_map(|(point, o)| o.map(|v| (*point, v)))
     .collect()
 }
 
+/// Lowest risk path, with non-negative risks it never revisits a cell
 fn find_shortest_path(
     cavern: &Cavern,
     start: Point,
@@ -77,6 +99,65 @@ fn find_shortest_path(
     )
 }
 
+/// Cheapest path from `start` to whichever of `targets` is closest
+pub fn shortest_to_any(
+    cavern: &Cavern,
+    start: Point,
+    targets: &[Point],
+) -> Option<(Vec<Point>, usize)> {
+    let targets: HashSet<Point> = targets.iter().copied().collect();
+
+    dijkstra(
+        &start,
+        |p: &Point| get_neighbors(cavern, *p),
+        |p: &Point| targets.contains(p),
+    )
+}
+
+pub fn manhattan(a: Point, b: Point) -> usize {
+    ((a.0 - b.0).abs() + (a.1 - b.1).abs()) as usize
+}
+
+pub fn chebyshev(a: Point, b: Point) -> usize {
+    (a.0 - b.0).abs().max((a.1 - b.1).abs()) as usize
+}
+
+/// A* with the manhattan distance as heuristic, admissible as every step costs at least 1
+pub fn find_shortest_path_astar(
+    cavern: &Cavern,
+    start: Point,
+    destination: Point,
+) -> Option<(Vec<Point>, usize)> {
+    astar(
+        &start,
+        |p: &Point| get_neighbors(cavern, *p),
+        |p: &Point| manhattan(*p, destination),
+        |p: &Point| *p == destination,
+    )
+}
+
+pub fn verify_path(cavern: &Cavern, path: &[Point]) -> Result<usize> {
+    let start = path.first().ok_or(anyhow!("empty path"))?;
+
+    if !cavern.contains_key(start) {
+        bail!("start {:?} outside of cavern", start);
+    }
+
+    path.windows(2).try_fold(0, |risk, step| {
+        let (from, to) = (step[0], step[1]);
+
+        if (from.0 - to.0).abs() + (from.1 - to.1).abs() != 1 {
+            bail!("{:?} -> {:?} is not a single step", from, to);
+        }
+
+        let entry_risk = cavern
+            .get(&to)
+            .ok_or(anyhow!("{:?} outside of cavern", to))?;
+
+        Ok(risk + entry_risk)
+    })
+}
+
 fn expand_cavern(cavern: &Cavern, dimensions: (usize, usize), factor: usize) -> (Cavern, Point) {
     let mut expanded = Cavern::new();
 
@@ -149,4 +230,112 @@ mod test {
         let shortest_path = find_shortest_path(&cavern, start, destination).unwrap();
         assert_eq!(shortest_path.1, 40);
     }
+
+    static SAMPLE_CAVERN: &str = r"1163751742
+        1381373672
+        2136511328
+        3694931569
+        7463417111
+        1319128137
+        1359912421
+        3125421639
+        1293138521
+        2311944581";
+
+    #[test]
+    fn verify_path_valid() {
+        let cavern = load_map(SAMPLE_CAVERN).unwrap();
+        let destination = get_destination(SAMPLE_CAVERN);
+        let (path, _) = find_shortest_path(&cavern, (0, 0), destination).unwrap();
+
+        assert_eq!(verify_path(&cavern, &path).unwrap(), 40);
+    }
+
+    #[test]
+    fn verify_path_disconnected() {
+        let cavern = load_map(SAMPLE_CAVERN).unwrap();
+        let path = vec![(0, 0), (0, 1), (1, 2)];
+
+        assert!(verify_path(&cavern, &path).is_err());
+    }
+
+    #[test]
+    fn shortest_path_is_simple() {
+        let map_raw = r"1911
+        1191
+        9111";
+
+        let cavern = load_map(map_raw).unwrap();
+        let destination = get_destination(map_raw);
+
+        let (path, risk) = find_shortest_path(&cavern, (0, 0), destination).unwrap();
+
+        assert_eq!(risk, 5);
+        assert_eq!(verify_path(&cavern, &path).unwrap(), risk);
+
+        let unique: HashSet<&Point> = path.iter().collect();
+        assert_eq!(unique.len(), path.len());
+    }
+
+    #[test]
+    fn from_grid_matches_parsed() {
+        let rows = vec![vec![1, 9, 1, 1], vec![1, 1, 9, 1], vec![9, 1, 1, 1]];
+        let map_raw = r"1911
+        1191
+        9111";
+
+        let cavern = from_grid(rows);
+        let parsed = load_map(map_raw).unwrap();
+        assert_eq!(cavern, parsed);
+
+        assert_eq!(cavern_dim(&cavern), get_dim(map_raw));
+        let destination = cavern_destination(&cavern);
+        assert_eq!(destination, get_destination(map_raw));
+
+        let (_, risk) = find_shortest_path(&cavern, (0, 0), destination).unwrap();
+        let (_, parsed_risk) = find_shortest_path(&parsed, (0, 0), destination).unwrap();
+        assert_eq!(risk, 5);
+        assert_eq!(risk, parsed_risk);
+    }
+
+    #[test]
+    fn distances() {
+        let origin = (0, 0);
+        let destination = get_destination(SAMPLE_CAVERN);
+
+        assert_eq!(manhattan(origin, origin), 0);
+        assert_eq!(chebyshev(origin, origin), 0);
+
+        assert_eq!(manhattan(origin, destination), 18);
+        assert_eq!(chebyshev(origin, destination), 9);
+        assert_eq!(manhattan(destination, (7, 2)), 9);
+        assert_eq!(chebyshev(destination, (7, 2)), 7);
+    }
+
+    #[test]
+    fn astar_matches_dijkstra() {
+        let cavern = load_map(SAMPLE_CAVERN).unwrap();
+        let destination = get_destination(SAMPLE_CAVERN);
+
+        let (path, risk) = find_shortest_path_astar(&cavern, (0, 0), destination).unwrap();
+
+        assert_eq!(risk, 40);
+        assert_eq!(verify_path(&cavern, &path).unwrap(), 40);
+    }
+
+    #[test]
+    fn shortest_to_any_picks_closest() {
+        let cavern = load_map(SAMPLE_CAVERN).unwrap();
+        let destination = get_destination(SAMPLE_CAVERN);
+
+        let (path, risk) = shortest_to_any(&cavern, (0, 0), &[destination, (2, 0)]).unwrap();
+        assert_eq!(path.last(), Some(&(2, 0)));
+        assert_eq!(risk, 3);
+
+        let (path, risk) = shortest_to_any(&cavern, (0, 0), &[destination]).unwrap();
+        assert_eq!(path.last(), Some(&destination));
+        assert_eq!(risk, 40);
+
+        assert_eq!(shortest_to_any(&cavern, (0, 0), &[]), None);
+    }
 }
@@ -1,27 +1,65 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+
+/// Maximum operator nesting depth accepted by `parse_packet`
+const MAX_DEPTH: usize = 256;
+
+/// Bits per value group, one continuation bit and four value bits
+const VALUE_GROUP_SIZE: usize = 5;
+
+/// Limits and value group size used while parsing a transmission
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseConfig {
+    pub max_depth: usize,
+    pub group_size: usize,
+}
+
+impl ParseConfig {
+    /// Bits of the smallest possible packet, a header and a single value group
+    fn min_packet_len(&self) -> usize {
+        6 + self.group_size
+    }
+}
+
+impl Default for ParseConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: MAX_DEPTH,
+            group_size: VALUE_GROUP_SIZE,
+        }
+    }
+}
 
 #[derive(Clone, Copy, Hash, Debug, PartialEq, Eq)]
-struct ValuePacket {
+pub struct ValuePacket {
     pub version: usize,
     pub value: usize,
+    pub group_size: usize,
     pub len: usize,
 }
 
 impl ValuePacket {
-    fn new(version: usize, raw: &str) -> Self {
+    /// Parse the value with groups of one continuation bit and `group_size - 1` value bits
+    pub fn with_group_size(version: usize, raw: &str, group_size: usize) -> Result<Self> {
+        Self::parse(version, raw, group_size, 0)
+    }
+
+    /// `offset` is the bit position of `raw` in the transmission, used for errors
+    fn parse(version: usize, raw: &str, group_size: usize, offset: usize) -> Result<Self> {
+        if group_size < 2 {
+            bail!("value group size must be at least 2, got {}", group_size);
+        }
+
         let mut value_raw: Vec<&str> = Vec::new();
 
         let mut i: usize = 0;
         loop {
-            let start = i * 5;
-            let end = i * 5 + 5;
-            let v = &raw[start..end];
-
-            if v.len() != 5 {
-                panic!("value packet parsing error");
-            }
+            let start = i * group_size;
+            let end = i * group_size + group_size;
+            let v = raw
+                .get(start..end)
+                .ok_or(anyhow!("incomplete value group at bit {}", offset + start))?;
 
-            value_raw.push(&v[1..5]);
+            value_raw.push(&v[1..group_size]);
 
             i += 1;
 
@@ -30,19 +68,20 @@ impl ValuePacket {
             }
         }
 
-        let value = binary_to_usize(value_raw.join("").as_str());
-        let len = i * 5 + 6;
+        let value = binary_to_usize(value_raw.join("").as_str(), offset)?;
+        let len = i * group_size + 6;
 
-        Self {
+        Ok(Self {
             version,
             value,
+            group_size,
             len,
-        }
+        })
     }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-enum OpType {
+pub enum OpType {
     Sum,
     Product,
     Minimum,
@@ -69,27 +108,67 @@ impl From<usize> for OpType {
     }
 }
 
+/// How an operator announces its sub-packets
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LengthType {
+    /// Length type id 0, total bits of the sub-packets in 15 bits
+    TotalBits,
+    /// Length type id 1, number of sub-packets in 11 bits
+    Count,
+}
+
 #[derive(Clone, Hash, Debug, PartialEq, Eq)]
-struct OperatorPacket {
+pub struct OperatorPacket {
     pub version: usize,
     pub op_type: OpType,
+    pub length_type: LengthType,
     pub sub_packets: Vec<Packet>,
     pub len: usize,
 }
 
 impl OperatorPacket {
-    fn new(version: usize, op_type: OpType, raw: &str) -> Self {
-        let length_type_id = &raw[0..1];
+    fn new(
+        version: usize,
+        op_type: OpType,
+        raw: &str,
+        config: ParseConfig,
+        offset: usize,
+    ) -> Result<Self> {
+        let length_type_id = raw
+            .get(0..1)
+            .ok_or(anyhow!("missing length type id at bit {}", offset))?;
         let mut sub_packets = Vec::new();
         let mut total_size = 1;
 
+        let length_type = match length_type_id {
+            "1" => LengthType::Count,
+            _ => LengthType::TotalBits,
+        };
+
         match length_type_id {
             "1" => {
-                let number_sub_packets = binary_to_usize(&raw[1..12]);
+                let number_sub_packets = binary_to_usize(
+                    raw.get(1..12).ok_or(anyhow!(
+                        "incomplete number of sub-packets at bit {}",
+                        offset + 1
+                    ))?,
+                    offset + 1,
+                )?;
                 total_size += 11;
                 let mut start: usize = 12;
+
+                let remaining = raw.len() - start;
+                if number_sub_packets * config.min_packet_len() > remaining {
+                    bail!(
+                        "{} sub-packets announced but only {} bits left at bit {}",
+                        number_sub_packets,
+                        remaining,
+                        offset + start
+                    );
+                }
+
                 for _ in 0..number_sub_packets {
-                    let packet = parse_packet(&raw[start..]);
+                    let packet = parse_packet_nested(&raw[start..], config, offset + start)?;
 
                     total_size += packet.len();
                     start += packet.len();
@@ -98,13 +177,19 @@ impl OperatorPacket {
                 }
             }
             "0" => {
-                let sub_packets_length = binary_to_usize(&raw[1..16]);
+                let sub_packets_length = binary_to_usize(
+                    raw.get(1..16).ok_or(anyhow!(
+                        "incomplete length of sub-packets at bit {}",
+                        offset + 1
+                    ))?,
+                    offset + 1,
+                )?;
                 total_size += 15;
                 let mut sub_packages_length_counter: usize = 0;
                 let mut start: usize = 16;
 
-                while sub_packets_length != sub_packages_length_counter {
-                    let packet = parse_packet(&raw[start..]);
+                while sub_packages_length_counter < sub_packets_length {
+                    let packet = parse_packet_nested(&raw[start..], config, offset + start)?;
 
                     start += packet.len();
                     sub_packages_length_counter += packet.len();
@@ -112,30 +197,109 @@ impl OperatorPacket {
 
                     sub_packets.push(packet);
                 }
+
+                if sub_packages_length_counter != sub_packets_length {
+                    bail!(
+                        "sub-packets exceed their announced length at bit {}",
+                        offset + start
+                    );
+                }
             }
             _ => unreachable!(),
         }
 
         let len = total_size + 6;
 
-        Self {
+        Ok(Self {
             version,
             op_type,
+            length_type,
             sub_packets,
             len,
-        }
+        })
     }
 
     pub fn version_sum(&self) -> usize {
         let sub_sum: usize = self.sub_packets.iter().map(|p| p.version_sum()).sum();
 
-        sub_sum + self.version as usize
+        sub_sum + self.version
     }
 
     pub fn value(&self) -> usize {
-        let compare = |op: OpType| -> usize {
-            let v1 = self.sub_packets[0].value();
-            let v2 = self.sub_packets[1].value();
+        let values: Vec<usize> = self.sub_packets.iter().map(|p| p.value()).collect();
+
+        self.op_type.apply(&values)
+    }
+
+    fn value_checked(&self, evaluated: &mut usize) -> Option<usize> {
+        let mut values = self
+            .sub_packets
+            .iter()
+            .map(|p| p.value_checked_counted(evaluated));
+
+        match self.op_type {
+            OpType::Sum => values.try_fold(0usize, |acc, v| acc.checked_add(v?)),
+            OpType::Product => {
+                // `None` once overflowed, a later zero factor still makes the product 0
+                let mut product = Some(1usize);
+
+                for v in values {
+                    let v = v?;
+
+                    if v == 0 {
+                        return Some(0);
+                    }
+
+                    product = product.and_then(|p| p.checked_mul(v));
+                }
+
+                product
+            }
+            _ => {
+                let values = values.collect::<Option<Vec<usize>>>()?;
+
+                self.op_type.apply_checked(&values)
+            }
+        }
+    }
+}
+
+impl OpType {
+    /// Type id of the operator in the packet header
+    pub fn type_id(&self) -> usize {
+        match self {
+            OpType::Sum => 0,
+            OpType::Product => 1,
+            OpType::Minimum => 2,
+            OpType::Maximum => 3,
+            OpType::Value => 4,
+            OpType::GreaterThan => 5,
+            OpType::LessThan => 6,
+            OpType::EqualTo => 7,
+        }
+    }
+
+    /// Like `apply`, `None` on overflow or a wrong number of values
+    pub fn apply_checked(&self, values: &[usize]) -> Option<usize> {
+        match self {
+            OpType::Sum => values.iter().try_fold(0usize, |acc, v| acc.checked_add(*v)),
+            OpType::Product => match values.contains(&0) {
+                true => Some(0),
+                false => values.iter().try_fold(1usize, |acc, v| acc.checked_mul(*v)),
+            },
+            OpType::Minimum => values.iter().min().copied(),
+            OpType::Maximum => values.iter().max().copied(),
+            OpType::Value => None,
+            _ if values.len() != 2 => None,
+            _ => Some(self.apply(values)),
+        }
+    }
+
+    /// Apply the operator to the values of the sub-packets
+    pub fn apply(&self, values: &[usize]) -> usize {
+        let compare = |op: &OpType| -> usize {
+            let v1 = values[0];
+            let v2 = values[1];
 
             let b = match op {
                 OpType::GreaterThan => v1 > v2,
@@ -147,18 +311,38 @@ impl OperatorPacket {
             b as usize
         };
 
-        match self.op_type {
-            OpType::Sum => self.sub_packets.iter().map(|v| v.value()).sum(),
-            OpType::Product => self.sub_packets.iter().fold(1, |acc, p| acc * p.value()),
-            OpType::Minimum => self.sub_packets.iter().map(|v| v.value()).min().unwrap(),
-            OpType::Maximum => self.sub_packets.iter().map(|v| v.value()).max().unwrap(),
-            _ => compare(self.op_type),
+        match self {
+            OpType::Sum => values.iter().sum(),
+            OpType::Product => values.iter().product(),
+            OpType::Minimum => *values.iter().min().unwrap(),
+            OpType::Maximum => *values.iter().max().unwrap(),
+            _ => compare(self),
         }
     }
 }
 
+/// Packet tree holding the computed value of every node, `None` where it has none
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
-enum Packet {
+pub enum ResultTree {
+    Value(usize),
+    Operator {
+        op_type: OpType,
+        value: Option<usize>,
+        sub_results: Vec<ResultTree>,
+    },
+}
+
+impl ResultTree {
+    pub fn value(&self) -> Option<usize> {
+        match self {
+            ResultTree::Value(v) => Some(*v),
+            ResultTree::Operator { value, .. } => *value,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Packet {
     Value(ValuePacket),
     Operator(OperatorPacket),
 }
@@ -166,7 +350,7 @@ enum Packet {
 impl Packet {
     pub fn version_sum(&self) -> usize {
         match self {
-            Packet::Value(v) => v.version as usize,
+            Packet::Value(v) => v.version,
             Packet::Operator(o) => o.version_sum(),
         }
     }
@@ -178,6 +362,171 @@ impl Packet {
         }
     }
 
+    /// Value of the packet, `None` on overflow or a malformed operator
+    pub fn value_checked(&self) -> Option<usize> {
+        self.value_checked_counted(&mut 0)
+    }
+
+    /// Like `value_checked`, counting the packets which actually got evaluated
+    fn value_checked_counted(&self, evaluated: &mut usize) -> Option<usize> {
+        *evaluated += 1;
+
+        match self {
+            Packet::Value(v) => Some(v.value),
+            Packet::Operator(o) => o.value_checked(evaluated),
+        }
+    }
+
+    pub fn evaluate_tree(&self) -> ResultTree {
+        match self {
+            Packet::Value(v) => ResultTree::Value(v.value),
+            Packet::Operator(o) => {
+                let sub_results: Vec<ResultTree> =
+                    o.sub_packets.iter().map(|p| p.evaluate_tree()).collect();
+
+                let values: Option<Vec<usize>> = sub_results.iter().map(|r| r.value()).collect();
+
+                ResultTree::Operator {
+                    op_type: o.op_type,
+                    value: values.and_then(|v| o.op_type.apply_checked(&v)),
+                    sub_results,
+                }
+            }
+        }
+    }
+
+    /// Bit stream of the packet, errors if a length field does not fit its width
+    pub fn to_bits(&self) -> Result<String> {
+        match self {
+            Packet::Value(v) => {
+                if v.group_size < 2 {
+                    bail!("value group size must be at least 2, got {}", v.group_size);
+                }
+
+                let value_bits = v.group_size - 1;
+                let digits = format!("{:b}", v.value);
+                let padding = "0".repeat((value_bits - digits.len() % value_bits) % value_bits);
+                let padded = format!("{}{}", padding, digits);
+
+                // keep leading zero groups the packet was parsed with
+                let parsed_groups = v.len.saturating_sub(6) / v.group_size;
+                let leading_groups = parsed_groups.saturating_sub(padded.len() / value_bits);
+                let leading = "0".repeat(leading_groups * value_bits);
+                let padded = format!("{}{}", leading, padded);
+
+                let groups = padded.as_bytes().chunks(value_bits).collect::<Vec<_>>();
+                let body: String = groups
+                    .iter()
+                    .enumerate()
+                    .map(|(i, g)| {
+                        let prefix = if i + 1 < groups.len() { "1" } else { "0" };
+                        format!("{}{}", prefix, std::str::from_utf8(g).unwrap())
+                    })
+                    .collect();
+
+                Ok(format!(
+                    "{:03b}{:03b}{}",
+                    v.version,
+                    OpType::Value.type_id(),
+                    body
+                ))
+            }
+            Packet::Operator(o) => {
+                let sub_bits = o
+                    .sub_packets
+                    .iter()
+                    .map(|p| p.to_bits())
+                    .collect::<Result<String>>()?;
+
+                let length_field = match o.length_type {
+                    LengthType::Count => {
+                        if o.sub_packets.len() >= 1 << 11 {
+                            bail!(
+                                "{} sub-packets do not fit into 11 bits",
+                                o.sub_packets.len()
+                            );
+                        }
+
+                        format!("1{:011b}", o.sub_packets.len())
+                    }
+                    LengthType::TotalBits => {
+                        if sub_bits.len() >= 1 << 15 {
+                            bail!(
+                                "{} bits of sub-packets do not fit into 15 bits",
+                                sub_bits.len()
+                            );
+                        }
+
+                        format!("0{:015b}", sub_bits.len())
+                    }
+                };
+
+                Ok(format!(
+                    "{:03b}{:03b}{}{}",
+                    o.version,
+                    o.op_type.type_id(),
+                    length_field,
+                    sub_bits
+                ))
+            }
+        }
+    }
+
+    /// Field names and bits of the top-level packet, sub-packets are listed as a whole
+    pub fn annotate_bits(&self) -> Result<Vec<(String, String)>> {
+        let bits = self.to_bits()?;
+        let field = |name: String, range: std::ops::Range<usize>| (name, bits[range].to_owned());
+
+        let mut fields = vec![
+            field("version".to_owned(), 0..3),
+            field("type id".to_owned(), 3..6),
+        ];
+
+        match self {
+            Packet::Value(v) => {
+                (6..bits.len())
+                    .step_by(v.group_size)
+                    .enumerate()
+                    .for_each(|(i, start)| {
+                        fields.push(field(
+                            format!("value group {}", i + 1),
+                            start..start + v.group_size,
+                        ))
+                    });
+            }
+            Packet::Operator(o) => {
+                fields.push(field("length type id".to_owned(), 6..7));
+
+                let mut start = match o.length_type {
+                    LengthType::Count => {
+                        fields.push(field("number of sub-packets".to_owned(), 7..18));
+                        18
+                    }
+                    LengthType::TotalBits => {
+                        fields.push(field("length of sub-packets".to_owned(), 7..22));
+                        22
+                    }
+                };
+
+                for (i, p) in o.sub_packets.iter().enumerate() {
+                    let len = p.to_bits()?.len();
+
+                    fields.push(field(format!("sub-packet {}", i + 1), start..start + len));
+                    start += len;
+                }
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// Hex transmission of the packet, padded to a nibble boundary
+    pub fn encode(&self) -> Result<String> {
+        Ok(hex_encode(&self.to_bits()?))
+    }
+
+    /// Number of bits the packet occupies
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         match self {
             Packet::Value(v) => v.len,
@@ -186,55 +535,119 @@ impl Packet {
     }
 }
 
-fn binary_to_usize(b: &str) -> usize {
-    usize::from_str_radix(b, 2).unwrap()
+impl std::fmt::Display for Packet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Packet::Value(_) => write!(f, "Value")?,
+            Packet::Operator(o) => write!(f, "Operator({:?})", o.op_type)?,
+        }
+
+        let value = self
+            .value_checked()
+            .map_or("?".to_owned(), |v| v.to_string());
+
+        write!(f, " version_sum={} value={}", self.version_sum(), value)
+    }
+}
+
+/// `offset` is the bit position of `b` in the transmission, used for errors
+fn binary_to_usize(b: &str, offset: usize) -> Result<usize> {
+    usize::from_str_radix(b, 2).map_err(|e| {
+        anyhow!(
+            "invalid number of {} bits at bit {}: {}",
+            b.len(),
+            offset,
+            e
+        )
+    })
 }
 
-fn parse_packet(input: &str) -> Packet {
-    let version = binary_to_usize(&input[0..3]);
-    let op_type_raw = binary_to_usize(&input[3..6]);
+pub fn parse_packet(input: &str) -> Result<Packet> {
+    parse_packet_with(input, ParseConfig::default())
+}
+
+pub fn parse_packet_with(input: &str, config: ParseConfig) -> Result<Packet> {
+    parse_packet_nested(input, config, 0)
+}
+
+/// `offset` is the bit position of `input` in the transmission, used for errors
+fn parse_packet_nested(input: &str, config: ParseConfig, offset: usize) -> Result<Packet> {
+    let header = input
+        .get(0..6)
+        .ok_or(anyhow!("incomplete packet header at bit {}", offset))?;
+    let version = binary_to_usize(&header[0..3], offset)?;
+    let op_type_raw = binary_to_usize(&header[3..6], offset + 3)?;
     let op_type = OpType::from(op_type_raw);
 
     if op_type == OpType::Value {
-        let packet = ValuePacket::new(version, &input[6..]);
-        return Packet::Value(packet);
+        let packet = ValuePacket::parse(version, &input[6..], config.group_size, offset + 6)?;
+        return Ok(Packet::Value(packet));
+    }
+
+    if config.max_depth == 0 {
+        bail!("maximum packet nesting depth exceeded at bit {}", offset);
     }
 
-    let packet = OperatorPacket::new(version, op_type, &input[6..]);
-    Packet::Operator(packet)
+    let nested = ParseConfig {
+        max_depth: config.max_depth - 1,
+        ..config
+    };
+    let packet = OperatorPacket::new(version, op_type, &input[6..], nested, offset + 6)?;
+    Ok(Packet::Operator(packet))
 }
 
-fn to_binary(c: char) -> &'static str {
-    match c {
-        '0' => "0000",
-        '1' => "0001",
-        '2' => "0010",
-        '3' => "0011",
-        '4' => "0100",
-        '5' => "0101",
-        '6' => "0110",
-        '7' => "0111",
-        '8' => "1000",
-        '9' => "1001",
-        'A' => "1010",
-        'B' => "1011",
-        'C' => "1100",
-        'D' => "1101",
-        'E' => "1110",
-        'F' => "1111",
-        _ => unreachable!(""),
-    }
+/// Parse a hex transmission allowing at most `max_depth` nested operator packets
+pub fn parse_transmission(input: &str, max_depth: usize) -> Result<Packet> {
+    parse_transmission_with(
+        input,
+        ParseConfig {
+            max_depth,
+            ..ParseConfig::default()
+        },
+    )
 }
 
-fn hex_decode(s: &str) -> String {
-    s.trim().chars().map(|c| to_binary(c)).collect()
+pub fn parse_transmission_with(input: &str, config: ParseConfig) -> Result<Packet> {
+    parse_packet_nested(&hex_decode(input)?, config, 0)
+}
+
+/// Number of bits in a hex transmission, including the padding after the packet
+pub fn transmission_bit_length(hex: &str) -> usize {
+    hex.trim().len() * 4
+}
+
+/// Bits of a hex string, accepting upper and lower case digits
+fn hex_decode(s: &str) -> Result<String> {
+    s.trim()
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            c.to_digit(16).map(|d| format!("{:04b}", d)).ok_or(anyhow!(
+                "invalid hex char '{}' at index {}",
+                c,
+                i
+            ))
+        })
+        .collect()
+}
+
+fn hex_encode(bits: &str) -> String {
+    let padding = "0".repeat((4 - bits.len() % 4) % 4);
+    let padded = format!("{}{}", bits, padding);
+
+    padded
+        .as_bytes()
+        .chunks(4)
+        .map(|c| {
+            let nibble = c.iter().fold(0, |acc, b| acc * 2 + (b - b'0'));
+            format!("{:X}", nibble)
+        })
+        .collect()
 }
 
 fn main() -> Result<()> {
     let input = std::fs::read_to_string("input.txt")?;
-    let decoded = hex_decode(&input);
-
-    let packet = parse_packet(&decoded);
+    let packet = parse_transmission(&input, MAX_DEPTH)?;
 
     println!("P1: {}", packet.version_sum());
     println!("P2: {}", packet.value());
@@ -251,20 +664,33 @@ mod test {
         let encoded = "D2FE28";
         let decoded = "110100101111111000101000";
 
-        assert_eq!(hex_decode(encoded), decoded)
+        assert_eq!(hex_decode(encoded).unwrap(), decoded)
+    }
+
+    #[test]
+    fn hex_decode_invalid_char() {
+        assert_eq!(hex_decode("d2fe28").unwrap(), hex_decode("D2FE28").unwrap());
+        assert!(parse_transmission("d2fe28", 10).is_ok());
+
+        assert_eq!(
+            hex_decode("D2XE28").unwrap_err().to_string(),
+            "invalid hex char 'X' at index 2"
+        );
+        assert!(parse_transmission("D2FE2G", 10).is_err());
     }
 
     #[test]
     fn parse_value_packet() {
         let encoded = "D2FE28";
-        let decoded = hex_decode(encoded);
+        let decoded = hex_decode(encoded).unwrap();
 
-        let packet = parse_packet(&decoded);
+        let packet = parse_packet(&decoded).unwrap();
         assert_eq!(
             packet,
             Packet::Value(ValuePacket {
                 version: 6,
                 value: 2021,
+                group_size: VALUE_GROUP_SIZE,
                 len: 21
             })
         )
@@ -273,24 +699,27 @@ mod test {
     #[test]
     fn parse_op_0() {
         let encoded = "38006F45291200";
-        let decoded = hex_decode(encoded);
+        let decoded = hex_decode(encoded).unwrap();
 
-        let packet = parse_packet(&decoded);
+        let packet = parse_packet(&decoded).unwrap();
         assert_eq!(
             packet,
             Packet::Operator(OperatorPacket {
                 len: 49,
                 op_type: OpType::LessThan,
+                length_type: LengthType::TotalBits,
                 version: 1,
                 sub_packets: vec![
                     Packet::Value(ValuePacket {
                         version: 6,
                         value: 10,
+                        group_size: VALUE_GROUP_SIZE,
                         len: 11
                     }),
                     Packet::Value(ValuePacket {
                         version: 2,
                         value: 20,
+                        group_size: VALUE_GROUP_SIZE,
                         len: 16
                     })
                 ]
@@ -301,33 +730,427 @@ mod test {
     #[test]
     fn parse_op_1() {
         let encoded = "EE00D40C823060";
-        let decoded = hex_decode(encoded);
+        let decoded = hex_decode(encoded).unwrap();
 
-        let packet = parse_packet(&decoded);
+        let packet = parse_packet(&decoded).unwrap();
         assert_eq!(
             packet,
             Packet::Operator(OperatorPacket {
                 len: 51,
                 op_type: OpType::Maximum,
+                length_type: LengthType::Count,
                 version: 7,
                 sub_packets: vec![
                     Packet::Value(ValuePacket {
                         version: 2,
                         value: 1,
+                        group_size: VALUE_GROUP_SIZE,
                         len: 11
                     }),
                     Packet::Value(ValuePacket {
                         version: 4,
                         value: 2,
+                        group_size: VALUE_GROUP_SIZE,
                         len: 11
                     }),
                     Packet::Value(ValuePacket {
                         version: 1,
                         value: 3,
+                        group_size: VALUE_GROUP_SIZE,
                         len: 11
                     })
                 ]
             })
         )
     }
+
+    #[test]
+    fn display_summary() {
+        let packet = parse_packet(&hex_decode("EE00D40C823060").unwrap()).unwrap();
+
+        assert_eq!(
+            packet.to_string(),
+            "Operator(Maximum) version_sum=14 value=3"
+        );
+    }
+
+    #[test]
+    fn display_without_value() {
+        assert_eq!(
+            operator(OpType::Minimum, &[]).to_string(),
+            "Operator(Minimum) version_sum=0 value=?"
+        );
+        assert_eq!(
+            operator(OpType::Sum, &[usize::MAX, 1]).to_string(),
+            "Operator(Sum) version_sum=0 value=?"
+        );
+    }
+
+    fn nested_sum(depth: usize) -> String {
+        // sum operator with a single sub-packet, innermost a literal 1
+        let operator = "000000100000000001";
+        let literal = "00010000001";
+
+        format!("{}{}", operator.repeat(depth), literal)
+    }
+
+    #[test]
+    fn parse_transmission_nested() {
+        let packet = parse_transmission(&hex_encode(&nested_sum(10)), 10).unwrap();
+
+        assert_eq!(packet.value(), 1);
+    }
+
+    #[test]
+    fn parse_transmission_too_deep() {
+        let hex = hex_encode(&nested_sum(100_000));
+
+        assert_eq!(
+            parse_transmission(&hex, MAX_DEPTH).unwrap_err().to_string(),
+            format!(
+                "maximum packet nesting depth exceeded at bit {}",
+                MAX_DEPTH * 18
+            )
+        );
+        assert!(parse_transmission(&hex_encode(&nested_sum(11)), 10).is_err());
+    }
+
+    #[test]
+    fn evaluate_tree_intermediates() {
+        // 1 + 3 == 2 * 2
+        let packet = parse_packet(&hex_decode("9C0141080250320F1802104A08").unwrap()).unwrap();
+
+        let tree = packet.evaluate_tree();
+        assert_eq!(tree.value(), packet.value_checked());
+        assert_eq!(tree.value(), Some(1));
+
+        match tree {
+            ResultTree::Operator {
+                op_type,
+                sub_results,
+                ..
+            } => {
+                assert_eq!(op_type, OpType::EqualTo);
+                assert_eq!(sub_results[0].value(), Some(4));
+                assert_eq!(sub_results[1].value(), Some(4));
+            }
+            _ => panic!("expected operator"),
+        }
+    }
+
+    #[test]
+    fn evaluate_tree_malformed_operator() {
+        let packet = Packet::Operator(OperatorPacket {
+            version: 0,
+            op_type: OpType::Sum,
+            length_type: LengthType::Count,
+            sub_packets: vec![
+                operator(OpType::LessThan, &[1, 2, 3]),
+                operator(OpType::Maximum, &[4, 5]),
+            ],
+            len: 0,
+        });
+
+        let tree = packet.evaluate_tree();
+        assert_eq!(tree.value(), None);
+
+        match tree {
+            ResultTree::Operator { sub_results, .. } => {
+                assert_eq!(sub_results[0].value(), None);
+                assert_eq!(sub_results[1].value(), Some(5));
+            }
+            _ => panic!("expected operator"),
+        }
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let packet = parse_packet(&hex_decode("D2FE28").unwrap()).unwrap();
+
+        assert_eq!(packet.encode().unwrap(), "D2FE28");
+        assert_eq!(
+            parse_packet(&hex_decode(&packet.encode().unwrap()).unwrap()).unwrap(),
+            packet
+        );
+
+        for hex in [
+            "38006F45291200",
+            "EE00D40C823060",
+            "9C0141080250320F1802104A08",
+        ] {
+            let packet = parse_packet(&hex_decode(hex).unwrap()).unwrap();
+            assert_eq!(
+                parse_packet(&hex_decode(&packet.encode().unwrap()).unwrap()).unwrap(),
+                packet
+            );
+        }
+    }
+
+    #[test]
+    fn encode_round_trip_count_operator() {
+        // sum announcing 2 sub-packets, the first literal 3 padded with a leading zero group
+        let bits = format!("0010001{:011b}{}{}", 2, "0001001000000011", "00010000101");
+        let packet = parse_packet(&bits).unwrap();
+
+        assert_eq!(packet.to_bits().unwrap(), bits);
+
+        let decoded = hex_decode(&packet.encode().unwrap()).unwrap();
+        assert_eq!(parse_packet(&decoded).unwrap(), packet);
+    }
+
+    #[test]
+    fn encode_out_of_range_length() {
+        let mut too_many = operator(OpType::Sum, &vec![0xffff; 1 << 11]);
+        assert!(too_many.to_bits().is_err());
+
+        if let Packet::Operator(o) = &mut too_many {
+            o.length_type = LengthType::TotalBits;
+        }
+        // 2048 literals of 26 bits exceed 15 bits
+        assert!(too_many.encode().is_err());
+
+        assert!(operator(OpType::Sum, &[1; 3]).encode().is_ok());
+    }
+
+    #[test]
+    fn value_packet_custom_group_size() {
+        // 6 = 01 10 in groups of one continuation and two value bits
+        let packet = ValuePacket::with_group_size(6, "101010", 3).unwrap();
+
+        assert_eq!(packet.value, 6);
+        assert_eq!(packet.len, 12);
+        assert!(ValuePacket::with_group_size(6, "101010", 1).is_err());
+    }
+
+    #[test]
+    fn transmission_custom_group_size() {
+        // sum of 6 and 1 announced by count, literals in groups of three bits
+        let bits = format!("0000001{:011b}{}{}", 2, "110100101010", "001100001");
+        let config = ParseConfig {
+            group_size: 3,
+            ..ParseConfig::default()
+        };
+
+        let packet = parse_transmission_with(&hex_encode(&bits), config).unwrap();
+        assert_eq!(packet.value(), 7);
+        assert_eq!(packet.len(), bits.len());
+        assert_eq!(packet.to_bits().unwrap(), bits);
+
+        let sub_packets: Vec<String> = packet
+            .annotate_bits()
+            .unwrap()
+            .into_iter()
+            .filter(|(name, _)| name.starts_with("sub-packet"))
+            .map(|(_, b)| b)
+            .collect();
+        assert_eq!(sub_packets, vec!["110100101010", "001100001"]);
+
+        let value = parse_packet_with("110100101010", config).unwrap();
+        assert_eq!(
+            value.annotate_bits().unwrap()[2..],
+            [
+                ("value group 1".to_owned(), "101".to_owned()),
+                ("value group 2".to_owned(), "010".to_owned()),
+            ]
+        );
+
+        // the default groups read the same bits differently
+        assert!(parse_transmission(&hex_encode(&bits), MAX_DEPTH).map_or(true, |p| p != packet));
+    }
+
+    #[test]
+    fn truncated_packet_reports_offset() {
+        // operator with two sub-packets, the second value packet cut after its first group
+        let bits = hex_decode("38006F45291200").unwrap();
+        let truncated = &bits[..22 + 11 + 6 + 5];
+
+        assert_eq!(
+            parse_packet(truncated).unwrap_err().to_string(),
+            "incomplete value group at bit 44"
+        );
+        assert_eq!(
+            parse_packet(&bits[..10]).unwrap_err().to_string(),
+            "incomplete length of sub-packets at bit 7"
+        );
+    }
+
+    #[test]
+    fn oversized_literal_reports_offset() {
+        // operator with a 68 bit literal as its only sub-packet
+        let literal = format!("110100{}01111", "11111".repeat(16));
+        let bits = format!("0010000{:015b}{}", literal.len(), literal);
+
+        assert_eq!(
+            parse_packet(&bits).unwrap_err().to_string(),
+            "invalid number of 68 bits at bit 28: number too large to fit in target type"
+        );
+    }
+
+    #[test]
+    fn inflated_sub_packet_count() {
+        // EE00D40C823060 announces 3 sub-packets, claim 4 instead
+        let bits = hex_decode("EE00D40C823060").unwrap();
+        let inflated = format!("{}{:011b}{}", &bits[..7], 4, &bits[18..]);
+
+        assert_eq!(
+            parse_packet(&inflated).unwrap_err().to_string(),
+            "4 sub-packets announced but only 38 bits left at bit 18"
+        );
+    }
+
+    #[test]
+    fn annotate_value_packet() {
+        let packet = parse_packet(&hex_decode("D2FE28").unwrap()).unwrap();
+
+        let annotated = packet.annotate_bits().unwrap();
+        let fields: Vec<(&str, &str)> = annotated
+            .iter()
+            .map(|(name, bits)| (name.as_str(), bits.as_str()))
+            .collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                ("version", "110"),
+                ("type id", "100"),
+                ("value group 1", "10111"),
+                ("value group 2", "11110"),
+                ("value group 3", "00101"),
+            ]
+        );
+    }
+
+    #[test]
+    fn annotate_operator_packet() {
+        let packet = parse_packet(&hex_decode("EE00D40C823060").unwrap()).unwrap();
+
+        let names: Vec<String> = packet
+            .annotate_bits()
+            .unwrap()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+
+        assert_eq!(
+            names,
+            [
+                "version",
+                "type id",
+                "length type id",
+                "number of sub-packets",
+                "sub-packet 1",
+                "sub-packet 2",
+                "sub-packet 3"
+            ]
+        );
+        assert_eq!(packet.annotate_bits().unwrap()[3].1, "00000000011");
+    }
+
+    #[test]
+    fn annotate_non_canonical_literal() {
+        // sum with two sub-packets, the first literal 3 padded with a leading zero group
+        let padded = format!("000100{}{}", "10000", "00011");
+        let bits = format!("0010001{:011b}{}{}", 2, padded, "00010000101");
+        let packet = parse_packet(&bits).unwrap();
+
+        let annotated = packet.annotate_bits().unwrap();
+        let joined: String = annotated.iter().map(|(_, b)| b.as_str()).collect();
+        assert_eq!(joined, bits);
+        assert_eq!(annotated[2], ("length type id".to_owned(), "1".to_owned()));
+        assert_eq!(annotated[3].0, "number of sub-packets");
+        assert_eq!(annotated[4].1, padded);
+
+        let sub_values: Vec<usize> = annotated
+            .iter()
+            .filter(|(name, _)| name.starts_with("sub-packet"))
+            .map(|(_, b)| parse_packet(b).unwrap().value())
+            .collect();
+        assert_eq!(sub_values, vec![3, 5]);
+
+        let literal = ValuePacket::with_group_size(6, "101010", 3).unwrap();
+        let reencoded = Packet::Value(literal).annotate_bits().unwrap();
+        let joined: String = reencoded.iter().map(|(_, b)| b.as_str()).collect();
+        assert_eq!(joined, "110100101010");
+    }
+
+    fn operator(op_type: OpType, values: &[usize]) -> Packet {
+        let sub_packets = values
+            .iter()
+            .map(|value| {
+                Packet::Value(ValuePacket {
+                    version: 0,
+                    value: *value,
+                    group_size: VALUE_GROUP_SIZE,
+                    len: 0,
+                })
+            })
+            .collect();
+
+        Packet::Operator(OperatorPacket {
+            version: 0,
+            op_type,
+            length_type: LengthType::Count,
+            sub_packets,
+            len: 0,
+        })
+    }
+
+    #[test]
+    fn value_checked_short_circuits() {
+        let product = operator(OpType::Product, &[0, 5, 7]);
+
+        let mut evaluated = 0;
+        assert_eq!(product.value_checked_counted(&mut evaluated), Some(0));
+        assert_eq!(evaluated, 2);
+
+        let mut evaluated = 0;
+        let sum = operator(OpType::Sum, &[0, 5, 7]);
+        assert_eq!(sum.value_checked_counted(&mut evaluated), Some(12));
+        assert_eq!(evaluated, 4);
+    }
+
+    #[test]
+    fn value_checked_overflow() {
+        assert_eq!(
+            operator(OpType::Product, &[usize::MAX, 2]).value_checked(),
+            None
+        );
+        assert_eq!(
+            operator(OpType::Sum, &[usize::MAX, 1]).value_checked(),
+            None
+        );
+
+        let packet = parse_packet(&hex_decode("9C0141080250320F1802104A08").unwrap()).unwrap();
+        assert_eq!(packet.value_checked(), Some(packet.value()));
+    }
+
+    #[test]
+    fn value_checked_zero_after_overflow() {
+        let product = operator(OpType::Product, &[usize::MAX, 2, 0]);
+
+        let mut evaluated = 0;
+        assert_eq!(product.value_checked_counted(&mut evaluated), Some(0));
+        assert_eq!(evaluated, 4);
+
+        assert_eq!(OpType::Product.apply_checked(&[usize::MAX, 2, 0]), Some(0));
+    }
+
+    #[test]
+    fn value_checked_malformed_operators() {
+        assert_eq!(operator(OpType::Minimum, &[]).value_checked(), None);
+        assert_eq!(operator(OpType::Maximum, &[]).value_checked(), None);
+        assert_eq!(operator(OpType::GreaterThan, &[1]).value_checked(), None);
+        assert_eq!(operator(OpType::EqualTo, &[1, 1, 1]).value_checked(), None);
+        assert_eq!(operator(OpType::LessThan, &[1, 2]).value_checked(), Some(1));
+    }
+
+    #[test]
+    fn transmission_padding() {
+        let packet = parse_transmission("D2FE28", MAX_DEPTH).unwrap();
+        let total = transmission_bit_length("D2FE28");
+
+        assert_eq!(total, 24);
+        assert_eq!(packet.len(), 21);
+        assert_eq!(total - packet.len(), 3);
+    }
 }
@@ -23,8 +23,14 @@ impl TryFrom<char> for Cucumber {
 type Pos = (usize, usize);
 type Floor = HashMap<Pos, Cucumber>;
 
-#[derive(Clone, Debug)]
-struct SeaFloor {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CycleInfo {
+    pub start: usize,
+    pub length: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SeaFloor {
     pub current: Floor,
     pub history: Vec<Floor>,
     pub width: usize,
@@ -40,13 +46,12 @@ impl SeaFloor {
         let current = input
             .lines()
             .enumerate()
-            .map(|(j, l)| {
+            .flat_map(|(j, l)| {
                 l.trim()
                     .chars()
                     .enumerate()
                     .map(move |(i, c)| ((j, i), Cucumber::try_from(c)))
             })
-            .flatten()
             .filter_map(|(pos, c)| match c {
                 Ok(c) => Some((pos, c)),
                 Err(_) => None,
@@ -113,17 +118,151 @@ impl SeaFloor {
         changed
     }
 
+    /// Step once and return how many cucumbers moved and how many were blocked
+    pub fn next_step_with_blocked(&mut self) -> (usize, usize) {
+        let attempted = self.current.len();
+        let moved = self.next_step();
+
+        (moved, attempted - moved)
+    }
+
     fn next_pos(&self, pos: &Pos, cucumber: &Cucumber) -> Pos {
         match cucumber {
             Cucumber::East => (pos.0, (pos.1 + 1) % self.width),
             Cucumber::South => ((pos.0 + 1) % self.depth, pos.1),
         }
     }
+
+    pub fn state_eq(&self, other: &Floor) -> bool {
+        self.current == *other
+    }
+
+    /// Step until the current floor repeats a state from the history
+    pub fn detect_cycle(&mut self, max_steps: usize) -> Result<CycleInfo> {
+        for _ in 0..max_steps {
+            self.next_step();
+
+            if let Some(start) = self.history.iter().position(|f| self.state_eq(f)) {
+                let length = self.history.len() - start;
+
+                return Ok(CycleInfo { start, length });
+            }
+        }
+
+        bail!("no cycle found within {} steps", max_steps)
+    }
+
+    /// Fraction of occupied cells in every row
+    pub fn row_density(&self) -> Vec<f32> {
+        let mut occupied = vec![0; self.depth];
+        self.current.keys().for_each(|(j, _)| occupied[*j] += 1);
+
+        occupied
+            .into_iter()
+            .map(|o| o as f32 / self.width as f32)
+            .collect()
+    }
+
+    /// Fraction of occupied cells in every column
+    pub fn col_density(&self) -> Vec<f32> {
+        let mut occupied = vec![0; self.width];
+        self.current.keys().for_each(|(_, i)| occupied[*i] += 1);
+
+        occupied
+            .into_iter()
+            .map(|o| o as f32 / self.depth as f32)
+            .collect()
+    }
+
+    /// Rows separated by `/` with runs of empty cells written as their length, e.g. `v3>>1vv>`
+    pub fn to_compact(&self) -> String {
+        self.render()
+            .lines()
+            .map(|row| {
+                let mut compact = String::new();
+                let mut empty = 0;
+
+                row.chars().for_each(|c| {
+                    if c == '.' {
+                        empty += 1;
+                        return;
+                    }
+
+                    if empty > 0 {
+                        compact.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    compact.push(c);
+                });
+
+                if empty > 0 {
+                    compact.push_str(&empty.to_string());
+                }
+
+                compact
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    pub fn from_compact(s: &str) -> Result<SeaFloor> {
+        let rows = s
+            .trim()
+            .split('/')
+            .map(|compact| {
+                let mut row = String::new();
+                let mut empty = String::new();
+
+                for c in compact.chars() {
+                    if c.is_ascii_digit() {
+                        empty.push(c);
+                        continue;
+                    }
+
+                    if !empty.is_empty() {
+                        row.push_str(&".".repeat(empty.parse()?));
+                        empty.clear();
+                    }
+
+                    match c {
+                        '>' | 'v' => row.push(c),
+                        _ => bail!("unknown char '{}' in compact floor", c),
+                    }
+                }
+
+                if !empty.is_empty() {
+                    row.push_str(&".".repeat(empty.parse()?));
+                }
+
+                Ok(row)
+            })
+            .collect::<Result<Vec<String>>>()?;
+
+        if rows.iter().any(|r| r.len() != rows[0].len()) {
+            bail!("compact floor rows differ in width");
+        }
+
+        Ok(SeaFloor::new(&rows.join("\n")))
+    }
+
+    fn render(&self) -> String {
+        (0..self.depth)
+            .map(|j| {
+                (0..self.width)
+                    .map(|i| match self.current.get(&(j, i)) {
+                        Some(Cucumber::East) => '>',
+                        Some(Cucumber::South) => 'v',
+                        None => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
-fn main() -> Result<()> {
-    let input = read_to_string("input.txt")?;
-    let mut sea_floor = SeaFloor::new(&input);
+pub fn solve(input: &str) -> Result<(usize, String)> {
+    let mut sea_floor = SeaFloor::new(input);
 
     let mut step: usize = 0;
     loop {
@@ -136,7 +275,108 @@ fn main() -> Result<()> {
         }
     }
 
+    Ok((step, sea_floor.render()))
+}
+
+fn main() -> Result<()> {
+    let input = read_to_string("input.txt")?;
+
+    let (step, _) = solve(&input)?;
+
     println!("step: {}", step);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static SAMPLE_FLOOR: &str = r"v...>>.vv>
+    .vv>>.vv..
+    >>.>v>...v
+    >>v>>.>.v.
+    v>v.vv.v..
+    >.>>..v...
+    .vv..>.>v.
+    v.v..>>v.v
+    ....v..v.>";
+
+    #[test]
+    fn solve_sample() {
+        let expected = [
+            "..>>v>vv..",
+            "..v.>>vv..",
+            "..>>v>>vv.",
+            "..>>>>>vv.",
+            "v......>vv",
+            "v>v....>>v",
+            "vvv.....>>",
+            ">vv......>",
+            ".>v.vv.v..",
+        ]
+        .join("\n");
+
+        let (step, render) = solve(SAMPLE_FLOOR).unwrap();
+
+        assert_eq!(step, 58);
+        assert_eq!(render, expected);
+    }
+
+    #[test]
+    fn detect_cycle_oscillating() {
+        let mut sea_floor = SeaFloor::new(">..");
+
+        let cycle = sea_floor.detect_cycle(10).unwrap();
+
+        assert_eq!(
+            cycle,
+            CycleInfo {
+                start: 0,
+                length: 3
+            }
+        );
+    }
+
+    #[test]
+    fn detect_cycle_stable() {
+        let mut sea_floor = SeaFloor::new(SAMPLE_FLOOR);
+
+        assert!(sea_floor.detect_cycle(10).is_err());
+        assert_eq!(sea_floor.detect_cycle(100).unwrap().length, 1);
+    }
+
+    #[test]
+    fn compact_round_trip() {
+        let sea_floor = SeaFloor::new(SAMPLE_FLOOR);
+
+        let compact = sea_floor.to_compact();
+        assert!(compact.starts_with("v3>>1vv>/1vv>>1vv2/"));
+
+        assert_eq!(SeaFloor::from_compact(&compact).unwrap(), sea_floor);
+        assert!(SeaFloor::from_compact("v2/>").is_err());
+        assert!(SeaFloor::from_compact("v2x").is_err());
+    }
+
+    #[test]
+    fn densities_sample() {
+        let sea_floor = SeaFloor::new(SAMPLE_FLOOR);
+
+        let rows = sea_floor.row_density();
+        assert_eq!(rows.len(), 9);
+        assert_eq!(rows[0], 0.6);
+        assert_eq!(rows[8], 0.3);
+
+        let cols = sea_floor.col_density();
+        assert_eq!(cols.len(), 10);
+        assert_eq!(cols[0], 6.0 / 9.0);
+        assert_eq!(cols[1], 5.0 / 9.0);
+    }
+
+    #[test]
+    fn blocked_first_step() {
+        let mut sea_floor = SeaFloor::new(SAMPLE_FLOOR);
+
+        assert_eq!(sea_floor.next_step_with_blocked(), (24, 25));
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{anyhow, Error, Result};
+use std::{collections::HashMap, str::FromStr};
+
+pub type Point = (isize, isize);
+
+pub const ORTHOGONAL: [Point; 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+pub const DIAGONAL: [Point; 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+/// Positions of the `offsets` around `point` which are part of the map
+pub fn neighbors<V>(map: &HashMap<Point, V>, point: Point, offsets: &[Point]) -> Vec<Point> {
+    let (x, y) = point;
+
+    offsets
+        .iter()
+        .map(|(i, j)| (x + i, y + j))
+        .filter(|neighbor| map.contains_key(neighbor))
+        .collect()
+}
+
+/// Grid of single digit values indexed by (row, col)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DigitGrid {
+    cells: HashMap<Point, usize>,
+}
+
+impl DigitGrid {
+    pub fn cells(&self) -> &HashMap<Point, usize> {
+        &self.cells
+    }
+
+    pub fn into_cells(self) -> HashMap<Point, usize> {
+        self.cells
+    }
+
+    pub fn get(&self, point: Point) -> Option<usize> {
+        self.cells.get(&point).copied()
+    }
+
+    pub fn orthogonal_neighbors(&self, point: Point) -> Vec<Point> {
+        neighbors(&self.cells, point, &ORTHOGONAL)
+    }
+
+    pub fn diagonal_neighbors(&self, point: Point) -> Vec<Point> {
+        neighbors(&self.cells, point, &DIAGONAL)
+    }
+
+    /// Orthogonal and diagonal neighbors
+    pub fn all_neighbors(&self, point: Point) -> Vec<Point> {
+        let mut all = self.orthogonal_neighbors(point);
+        all.extend(self.diagonal_neighbors(point));
+
+        all
+    }
+}
+
+impl FromStr for DigitGrid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let cells = s
+            .lines()
+            .enumerate()
+            .flat_map(|(i, l)| {
+                l.trim().chars().enumerate().map(move |(j, c)| {
+                    let d =
+                        c.to_digit(10)
+                            .ok_or(anyhow!("invalid char '{}' in ({}, {})", c, i, j))?
+                            as usize;
+                    Ok(((i as isize, j as isize), d))
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { cells })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static GRID: &str = r"123
+    456
+    789";
+
+    #[test]
+    fn parse_digits() {
+        let grid = DigitGrid::from_str(GRID).unwrap();
+
+        assert_eq!(grid.cells().len(), 9);
+        assert_eq!(grid.get((1, 2)), Some(6));
+        assert_eq!(grid.get((3, 0)), None);
+    }
+
+    #[test]
+    fn parse_invalid_char() {
+        assert_eq!(
+            DigitGrid::from_str("12\n3x").unwrap_err().to_string(),
+            "invalid char 'x' in (1, 1)"
+        );
+    }
+
+    #[test]
+    fn neighbor_counts() {
+        let grid = DigitGrid::from_str(GRID).unwrap();
+
+        let corner = (0, 0);
+        let edge = (0, 1);
+        let center = (1, 1);
+
+        assert_eq!(grid.orthogonal_neighbors(corner).len(), 2);
+        assert_eq!(grid.orthogonal_neighbors(edge).len(), 3);
+        assert_eq!(grid.orthogonal_neighbors(center).len(), 4);
+
+        assert_eq!(grid.diagonal_neighbors(corner).len(), 1);
+        assert_eq!(grid.diagonal_neighbors(edge).len(), 2);
+        assert_eq!(grid.diagonal_neighbors(center).len(), 4);
+
+        assert_eq!(grid.all_neighbors(corner).len(), 3);
+        assert_eq!(grid.all_neighbors(edge).len(), 5);
+        assert_eq!(grid.all_neighbors(center).len(), 8);
+    }
+}